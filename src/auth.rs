@@ -1,13 +1,103 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+
 use rocket::{async_trait, Request};
 use rocket::http::Status;
 use rocket::outcome::Outcome::Forward;
 use rocket::request::{FromRequest, Outcome};
+use serde::{Deserialize, Serialize};
+
+use crate::responses::{HandlerError, HttpResult};
+
+// Granted to any known token, routes check what it may do with `require` and who is acting with `principal.id`.
+pub struct Authorization {
+    pub principal: Principal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Publish,
+    Yank,
+    Delete,
+    // Everything the other scopes allow, and destructive operations such as overwriting a published version.
+    Admin,
+}
 
-pub struct Authorization;
+impl Scope {
+    fn describe(&self) -> &'static str {
+        match self {
+            Scope::Publish => "publish",
+            Scope::Yank => "yank versions",
+            Scope::Delete => "delete versions",
+            Scope::Admin => "administrate",
+        }
+    }
+}
+
+// Who a token belongs to, what it may do, and in which groups.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Principal {
+    // Groups are owned by principals, every token of a principal acts as it.
+    #[serde(rename = "principal")]
+    pub id: String,
+    // Group id prefixes, `com.example` covers `com.example` and `com.example.tools` but not `com.examples`.
+    // `*` covers every group.
+    pub groups: Vec<String>,
+    pub scopes: Vec<Scope>,
+}
 
+impl Principal {
+    pub fn allows(&self, scope: Scope, group: &str) -> bool {
+        let scoped = self.scopes.iter().any(|it| *it == scope || *it == Scope::Admin);
+
+        scoped && self.groups.iter().any(|prefix| {
+            prefix == "*" || group.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+    }
+}
+
+impl Authorization {
+    pub fn require(&self, scope: Scope, group: &str) -> HttpResult<()> {
+        if !self.principal.allows(scope, group) {
+            return Err(HandlerError::new(
+                "Forbidden".into(),
+                Some(format!("The token may not {} in '{}'.", scope.describe(), group)),
+                Status::Forbidden,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// Managed as `Arc<dyn Authorizer>`, requests are authorized concurrently so implementations guard their own state and
+// must not wait on the disk.
+#[async_trait]
 pub trait Authorizer: Send + Sync {
-    fn is_authorized(&self, request: &Request, token: &str) -> bool;
+    // None when the token is unknown.
+    async fn authorize(&self, request: &Request<'_>, token: &str) -> Option<Principal>;
+}
+
+// Asks each authorizer in turn, the first that knows the token decides who it belongs to.
+pub struct AuthorizerChain(pub Vec<Arc<dyn Authorizer>>);
+
+#[async_trait]
+impl Authorizer for AuthorizerChain {
+    async fn authorize(&self, request: &Request<'_>, token: &str) -> Option<Principal> {
+        for authorizer in &self.0 {
+            if let Some(principal) = authorizer.authorize(request, token).await {
+                return Some(principal);
+            }
+        }
+
+        None
+    }
+}
+
+fn bearer_token<'r>(request: &'r Request<'_>) -> Option<&'r str> {
+    request.headers().get("Authorization")
+        .next()?
+        .strip_prefix("Bearer ")
 }
 
 #[async_trait]
@@ -17,27 +107,43 @@ impl<'r> FromRequest<'r> for Authorization {
     async fn from_request(
         request: &'r Request<'_>
     ) -> Outcome<Self, Self::Error> {
-        let auth_header = if let Some(x) = request.headers().get("Authorization")
-            .next() {
+        let auth_header = if let Some(x) = bearer_token(request) {
             x
         } else {
             return Forward(Status::Unauthorized);
         };
 
-        let auth_header = if let Some(result) = auth_header.strip_prefix("Bearer ") {
-            result
-        } else {
-            return Forward(Status::Unauthorized)
-        };
+        let authorizer = request.rocket().state::<Arc<dyn Authorizer>>().expect("No authorizer provided!");
 
-        let authorizer = request.rocket().state::<Arc<Mutex<Box<dyn Authorizer>>>>().expect("No authorizer provided!");
+        match authorizer.authorize(request, auth_header).await {
+            Some(principal) => Outcome::Success(Authorization { principal }),
+            None => Forward(Status::Unauthorized),
+        }
+    }
+}
 
-        let authorized = authorizer.lock().unwrap().is_authorized(request, auth_header);
+#[cfg(test)]
+mod tests {
+    use crate::auth::{Principal, Scope};
 
-        if authorized {
-            Outcome::Success(Authorization)
-        } else {
-            Forward(Status::Unauthorized)
-        }
+    #[test]
+    fn test_principal() {
+        let principal = Principal {
+            id: "ci".into(),
+            groups: vec!["com.example".into()],
+            scopes: vec![Scope::Publish, Scope::Yank],
+        };
+        assert!(principal.allows(Scope::Publish, "com.example"));
+        assert!(principal.allows(Scope::Yank, "com.example.tools"));
+        assert!(!principal.allows(Scope::Publish, "com.examples"));
+        assert!(!principal.allows(Scope::Publish, "com"));
+        assert!(!principal.allows(Scope::Delete, "com.example"));
+
+        let admin = Principal {
+            id: "admin".into(),
+            groups: vec!["*".into()],
+            scopes: vec![Scope::Admin],
+        };
+        assert!(admin.allows(Scope::Delete, "org.anything"));
     }
-}
\ No newline at end of file
+}
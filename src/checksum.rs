@@ -0,0 +1,110 @@
+use std::io;
+use std::io::Write;
+
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::types::Checksums;
+
+// Extensions of the Maven style sidecar files written next to every published artifact.
+pub const SIDECAR_EXTENSIONS: [&str; 3] = ["sha256", "sha1", "md5"];
+
+// Splits a sidecar name such as `test1.jar.sha256` into the file it describes and its extension.
+pub fn sidecar_target(file_name: &str) -> Option<(&str, &'static str)> {
+    SIDECAR_EXTENSIONS.iter().find_map(|extension| {
+        file_name.strip_suffix(extension)
+            .and_then(|rest| rest.strip_suffix('.'))
+            .map(|target| (target, *extension))
+    })
+}
+
+pub fn is_sidecar(file_name: &str) -> bool {
+    sidecar_target(file_name).is_some()
+}
+
+// Sidecars hold the hex digest optionally followed by the file name, as written by sha256sum and friends.
+pub fn parse_sidecar(content: &str) -> Option<String> {
+    content.split_whitespace()
+        .next()
+        .map(|it| it.to_lowercase())
+}
+
+// Hashes everything written through it, so a file can be digested while it is streamed to disk.
+pub struct ChecksumWriter<W: Write> {
+    inner: W,
+    sha256: Sha256,
+    sha1: Sha1,
+    md5: Md5,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    pub fn new(inner: W) -> ChecksumWriter<W> {
+        ChecksumWriter {
+            inner,
+            sha256: Sha256::new(),
+            sha1: Sha1::new(),
+            md5: Md5::new(),
+        }
+    }
+
+    pub fn finish(self) -> (W, Checksums) {
+        (self.inner, Checksums {
+            sha256: format!("{:x}", self.sha256.finalize()),
+            sha1: format!("{:x}", self.sha1.finalize()),
+            md5: format!("{:x}", self.md5.finalize()),
+        })
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+
+        self.sha256.update(&buf[..written]);
+        self.sha1.update(&buf[..written]);
+        self.md5.update(&buf[..written]);
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::checksum::{is_sidecar, parse_sidecar, sidecar_target, ChecksumWriter};
+
+    #[test]
+    fn test_checksum_writer() {
+        let mut writer = ChecksumWriter::new(Vec::new());
+        writer.write_all(b"hello world").unwrap();
+
+        let (content, checksums) = writer.finish();
+
+        assert_eq!(content, b"hello world");
+        assert_eq!(checksums.sha256, "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9");
+        assert_eq!(checksums.sha1, "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed");
+        assert_eq!(checksums.md5, "5eb63bbbe01eeed093cb22bb8f5acdc3");
+    }
+
+    #[test]
+    fn test_is_sidecar() {
+        assert!(is_sidecar("test1.jar.sha256"));
+        assert!(is_sidecar("test1.jar.md5"));
+        assert!(!is_sidecar("test1.jar"));
+        assert!(!is_sidecar("notamd5"));
+        assert_eq!(sidecar_target("test1.jar.sha1"), Some(("test1.jar", "sha1")));
+    }
+
+    #[test]
+    fn test_parse_sidecar() {
+        assert_eq!(parse_sidecar("ABCDEF\n"), Some("abcdef".to_string()));
+        assert_eq!(parse_sidecar("abcdef  test1.jar\n"), Some("abcdef".to_string()));
+        assert_eq!(parse_sidecar(""), None);
+    }
+}
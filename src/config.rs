@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+// Server behaviour that is not part of the public repository metadata, read from data/server.json.
+// Every field has a default so the file is optional.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub bundle_limits: BundleLimits,
+    // Publishers trusted to sign bundles, keyed by the exact group id they may sign for.
+    pub publishers: HashMap<String, PublisherKeys>,
+    pub snapshots: SnapshotConfig,
+    // What happens to bundles whose parents are unpublished, yanked or would close a cycle.
+    pub parent_policy: ParentPolicy,
+    pub metadata_store: MetadataStoreKind,
+    // How bearer tokens are checked, `jwt` verifies them with the keys in `jwt` instead of the token store.
+    pub authorizer: AuthorizerKind,
+    pub jwt: JwtConfig,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorizerKind {
+    // Tokens from data/tokens.json and the environment.
+    #[default]
+    Tokens,
+    Jwt,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct JwtConfig {
+    // Keys JWTs are verified with, a JWT naming a key id in its `kid` header is only verified with that key.
+    pub keys: Vec<JwtKey>,
+    // A JWT must be minted for one of these audiences.
+    pub audience: Vec<String>,
+    pub issuer: Option<String>,
+    // Seconds of clock drift tolerated when checking `exp` and `nbf`.
+    pub leeway: u64,
+    // Claim holding the group id prefixes the JWT may act in.
+    pub groups_claim: String,
+    // Claim holding the scopes of the JWT, those without it may only publish.
+    pub scopes_claim: String,
+    // Whether tokens from the environment and the token routes are accepted next to JWTs.
+    pub fallback_to_tokens: bool,
+}
+
+impl Default for JwtConfig {
+    fn default() -> Self {
+        JwtConfig {
+            keys: Vec::new(),
+            audience: Vec::new(),
+            issuer: None,
+            leeway: 60,
+            groups_claim: "groups".into(),
+            scopes_claim: "scopes".into(),
+            fallback_to_tokens: false,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+pub struct JwtKey {
+    pub id: Option<String>,
+    pub algorithm: JwtAlgorithm,
+    // Relative to the data directory. The raw secret for HS256, a PEM encoded public key otherwise.
+    pub file: PathBuf,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+pub enum JwtAlgorithm {
+    HS256,
+    RS256,
+    EdDSA,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataStoreKind {
+    // data/metadata.json, with changes journaled to data/metadata.json.journal.
+    #[default]
+    Json,
+    // data/metadata.db, filled from data/metadata.json with `ext-server migrate-metadata`.
+    Sqlite,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParentPolicy {
+    #[default]
+    Reject,
+    // The bundle is published and the problems are returned to the publisher as warnings.
+    Warn,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PublisherKeys {
+    // Hex encoded ed25519 public keys.
+    pub keys: Vec<String>,
+    // Unsigned bundles are refused for the group when set.
+    pub require_signature: bool,
+}
+
+// Limits applied to uploaded extension bundles before anything is written to disk.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct BundleLimits {
+    pub max_entries: usize,
+    // Decompressed size of a single entry, in bytes.
+    pub max_entry_size: u64,
+    // Decompressed size of the whole bundle, in bytes.
+    pub max_total_size: u64,
+    // Largest allowed ratio of decompressed to compressed size for a single entry.
+    pub max_compression_ratio: u64,
+}
+
+impl Default for BundleLimits {
+    fn default() -> Self {
+        BundleLimits {
+            max_entries: 256,
+            max_entry_size: 256 * 1024 * 1024,
+            max_total_size: 512 * 1024 * 1024,
+            max_compression_ratio: 100,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    // Number of builds kept for each snapshot version, older builds are deleted when a new one is published.
+    pub retention: usize,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        SnapshotConfig {
+            retention: 10,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn load<T: Into<PathBuf>>(path: T) -> Result<ServerConfig, io::Error> {
+        let path = path.into();
+        if !Path::new(&path).exists() {
+            return Ok(ServerConfig::default());
+        }
+
+        let file = File::open(path)?;
+
+        serde_json::from_reader(file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
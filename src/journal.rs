@@ -0,0 +1,132 @@
+use std::fs;
+use std::fs::{create_dir_all, File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+// Writes next to the destination first and renames over it, a crash leaves either the old or the new content.
+pub fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.tmp", hex::encode(rand::random::<[u8; 8]>())));
+    let temporary = PathBuf::from(temporary);
+
+    let mut file = File::create(&temporary)?;
+    let written = file.write_all(content)
+        .and_then(|_| file.sync_all())
+        .and_then(|_| fs::rename(&temporary, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+
+    written
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    sequence: u64,
+    change: T,
+}
+
+// Append only log of changes, one JSON line each, synced to disk before a change is applied.
+pub struct Journal {
+    file: File,
+    entries: usize,
+}
+
+impl Journal {
+    pub fn open(path: &Path) -> io::Result<Journal> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        Ok(Journal {
+            file: OpenOptions::new().create(true).append(true).open(path)?,
+            entries: 0,
+        })
+    }
+
+    // Changes in the order they were written, a line torn by a crash ends the journal.
+    pub fn read<T: DeserializeOwned>(path: &Path) -> io::Result<Vec<(u64, T)>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut changes = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            match serde_json::from_str::<Entry<T>>(&line?) {
+                Ok(entry) => changes.push((entry.sequence, entry.change)),
+                Err(_) => break,
+            }
+        }
+
+        Ok(changes)
+    }
+
+    pub fn append<T: Serialize>(&mut self, sequence: u64, change: &T) -> io::Result<()> {
+        let mut line = serde_json::to_vec(&Entry {
+            sequence,
+            change,
+        })?;
+        line.push(b'\n');
+
+        self.file.write_all(&line)?;
+        self.file.sync_data()?;
+        self.entries += 1;
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries
+    }
+
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_data()?;
+        self.entries = 0;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    use crate::journal::{write_atomically, Journal};
+
+    #[test]
+    fn test_journal() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("changes.journal");
+
+        let mut journal = Journal::open(&path).unwrap();
+        journal.append(1, &"first".to_string()).unwrap();
+        journal.append(2, &"second".to_string()).unwrap();
+        assert_eq!(journal.len(), 2);
+
+        // A crash in the middle of a write leaves a partial line behind.
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"sequence\":3,\"cha").unwrap();
+        let changes: Vec<(u64, String)> = Journal::read(&path).unwrap();
+        assert_eq!(changes, vec![(1, "first".to_string()), (2, "second".to_string())]);
+
+        journal.clear().unwrap();
+        journal.append(3, &"third".to_string()).unwrap();
+        let changes: Vec<(u64, String)> = Journal::read(&path).unwrap();
+        assert_eq!(changes, vec![(3, "third".to_string())]);
+
+        let snapshot = directory.path().join("snapshot.json");
+        write_atomically(&snapshot, b"old").unwrap();
+        write_atomically(&snapshot, b"new").unwrap();
+        assert_eq!(std::fs::read(&snapshot).unwrap(), b"new");
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 2);
+    }
+}
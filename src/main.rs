@@ -2,41 +2,55 @@
 extern crate rocket;
 
 mod auth;
+mod checksum;
+mod config;
+mod journal;
+mod jwt;
 mod route;
 mod metadata;
+mod ownership;
+mod publish;
+mod resolve;
 mod search;
+mod signature;
+mod snapshot;
+mod token;
 mod types;
+mod version;
 mod responses;
 
 use std::env;
 use std::fs::File;
-use std::ops::Deref;
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use rocket::{Request, Response, Rocket};
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::http::Header;
 
-use auth::Authorizer;
+use auth::{Authorizer, AuthorizerChain, Principal, Scope};
+use config::{AuthorizerKind, MetadataStoreKind, ServerConfig};
+use jwt::JwtAuthorizer;
+use metadata::json::JsonStore;
+use metadata::sqlite::SqliteStore;
 use metadata::MetadataHandler;
+use ownership::OwnershipStore;
+use publish::{PublishLocks, Staging};
 use route::metadata::ExtensionMetadataServer;
+use route::owners::GroupOwnerServer;
 use route::registry::ExtensionFileServer;
+use route::resolve::ExtensionResolveServer;
 use route::search::ExtensionSearchServer;
+use route::tokens::TokenServer;
 use search::search::SearchHandler;
+use signature::RepositorySigner;
+use token::TokenStore;
 use types::{ExtensionIdentifier, RepositoryMetadata};
 
-struct BasicAuth(
-    String
-);
-
-impl Authorizer for BasicAuth {
-    fn is_authorized(&self, request: &Request, token: &str) -> bool {
-        let real_token = &self.0;
-
-        real_token == token
-    }
-}
+// How often downloads and token uses noted in memory are saved, at most this many seconds of them are lost in a crash.
+const FLUSH_SECONDS: u64 = 10;
 
 pub struct CORS;
 
@@ -53,34 +67,121 @@ impl Fairing for CORS {
         res.set_header(Header::new("Access-Control-Allow-Origin", "*"));
         res.set_header(Header::new("Access-Control-Allow-Methods", "GET, POST, PUT, DELETE, OPTIONS"));
         res.set_header(Header::new("Access-Control-Allow-Headers", "Content-Type, Authorization"));
+        res.set_header(Header::new("Access-Control-Expose-Headers", "X-Signature"));
     }
 }
 
 #[rocket::main]
 async fn main() {
+    if env::args().nth(1).as_deref() == Some("migrate-metadata") {
+        return migrate_metadata();
+    }
+
     let repository_metadata = File::open("data/config.json").expect("No config file setup for this repository! Please define it in data/config.json");
     let repository_metadata: RepositoryMetadata = serde_json::from_reader(repository_metadata).expect("Invalid config.json in data/config.json.");
+    let server_config = ServerConfig::load("data/server.json").expect("Invalid server.json in data/server.json.");
+    let repository_signer = RepositorySigner::load_or_create("data/repository.key").expect("Invalid repository key in data/repository.key.");
+    let mut token_store = TokenStore::load("data/tokens.json").expect("Invalid tokens.json in data/tokens.json.");
+    // Tokens from the environment are not bound to any group, AUTH_TOKEN publishes and ADMIN_TOKEN may do anything.
+    if let Ok(token) = env::var("AUTH_TOKEN") {
+        token_store.insert_environment(token, Principal {
+            id: "AUTH_TOKEN".into(),
+            groups: vec!["*".into()],
+            scopes: vec![Scope::Publish],
+        });
+    }
+    if let Ok(token) = env::var("ADMIN_TOKEN") {
+        token_store.insert_environment(token, Principal {
+            id: "ADMIN_TOKEN".into(),
+            groups: vec!["*".into()],
+            scopes: vec![Scope::Admin],
+        });
+    }
+    // Without any token the token routes cannot be used either, nothing could ever be published.
+    if server_config.authorizer == AuthorizerKind::Tokens && token_store.is_empty() {
+        eprintln!("WARNING: No AUTH_TOKEN or ADMIN_TOKEN in the environment and no tokens in data/tokens.json, every request needing authorization will be rejected. Set ADMIN_TOKEN to create tokens.");
+    }
+    let token_store = Arc::new(token_store);
+    let authorizer: Arc<dyn Authorizer> = match server_config.authorizer {
+        AuthorizerKind::Tokens => token_store.clone(),
+        AuthorizerKind::Jwt => {
+            let jwt = Arc::new(JwtAuthorizer::load(&server_config.jwt, Path::new("data")).expect("Invalid jwt config in data/server.json."));
+            // Only JWTs are accepted unless tokens from the environment and the /tokens routes are asked for too.
+            if server_config.jwt.fallback_to_tokens {
+                Arc::new(AuthorizerChain(vec![jwt, token_store.clone()]))
+            } else {
+                jwt
+            }
+        }
+    };
+    let metadata_handler = match server_config.metadata_store {
+        MetadataStoreKind::Json => MetadataHandler::hydrate_cache("data/metadata.json").expect("Invalid metadata in data/metadata.json."),
+        MetadataStoreKind::Sqlite => SqliteStore::open("data/metadata.db")
+            .and_then(|store| MetadataHandler::open(Box::new(store)))
+            .expect("Invalid metadata in data/metadata.db."),
+    };
+
+    // Downloads and token uses are only noted in memory when a request is served, they are saved every few seconds instead.
+    let downloads = metadata_handler.clone();
+    let uses = token_store.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(FLUSH_SECONDS));
+        loop {
+            interval.tick().await;
+            let downloads = downloads.clone();
+            let uses = uses.clone();
+            // Failed flushes keep what they could not save for the next one.
+            let _ = tokio::task::spawn_blocking(move || {
+                let _ = uses.flush_uses();
+                downloads.flush_downloads()
+            }).await;
+        }
+    });
+
+    // A publish interrupted by a crash leaves its staging directory behind, nothing is being published yet.
+    Staging::clean_up().expect("Could not remove leftover staging directories in static/.staging.");
 
     let rocket = Rocket::build()
         .attach(CORS)
         .configure(rocket::Config::figment().merge((
-            "port", u16::from_str(&*env::var("PORT").unwrap_or("8080".into())).expect("Invalid $PORT env variable defined, not a u16.")
+            "port", u16::from_str(&env::var("PORT").unwrap_or("8080".into())).expect("Invalid $PORT env variable defined, not a u16.")
         )))
         .mount("/", ExtensionFileServer)
         .mount("/", ExtensionMetadataServer)
         .mount("/", ExtensionSearchServer)
+        .mount("/", ExtensionResolveServer)
+        .mount("/", TokenServer)
+        .mount("/", GroupOwnerServer)
         .mount("/", routes![home])
-        .manage(Arc::new(Mutex::new(Box::new(BasicAuth(env::var("AUTH_TOKEN").expect("No Auth Token in environment. Set with AUTH_TOKEN"))) as Box<dyn Authorizer>)))
-        .manage(MetadataHandler::hydrate_cache("data/metadata.json").unwrap())
+        .manage(authorizer)
+        .manage(token_store)
+        .manage(metadata_handler)
         .manage(repository_metadata)
+        .manage(server_config)
+        .manage(repository_signer)
+        .manage(PublishLocks::default())
+        .manage(OwnershipStore::load("data/owners.json").expect("Invalid owners.json in data/owners.json."))
         .manage(Arc::new(Mutex::new(SearchHandler::<ExtensionIdentifier>::hydrate_cache("data/search_index.json").unwrap())))
         .launch().await.unwrap();
 
+    // Every change but the latest downloads and token uses is stored already, shutting down folds the journal into
+    // data/metadata.json.
     let handler: &MetadataHandler = rocket.state().unwrap();
-    handler.persist_to("data/metadata.json").unwrap();
+    handler.flush_downloads().unwrap();
+    handler.checkpoint().unwrap();
+    let token_store: &Arc<TokenStore> = rocket.state().unwrap();
+    token_store.flush_uses().unwrap();
+}
+
+// Copies data/metadata.json, including the changes in its journal, into a new data/metadata.db.
+fn migrate_metadata() {
+    assert!(!Path::new("data/metadata.db").exists(), "data/metadata.db exists already, remove it to migrate again.");
+
+    let mut from = JsonStore::open("data/metadata.json").expect("Invalid metadata in data/metadata.json.");
+    let mut to = SqliteStore::open("data/metadata.db").expect("Unable to create data/metadata.db.");
+    let count = metadata::migrate(&mut from, &mut to).expect("Unable to migrate the metadata.");
 
-    let search_handler: &Arc<Mutex<SearchHandler<ExtensionIdentifier>>> = rocket.state().unwrap();
-    search_handler.lock().unwrap().persist_to("data/search_index.json").unwrap();
+    println!("Migrated {} extensions to data/metadata.db, set \"metadata_store\": \"sqlite\" in data/server.json to use it.", count);
 }
 
 #[get("/")]
@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::journal::{write_atomically, Journal};
+use crate::metadata::{Change, Inner, MetadataStore};
+
+// How many changes the journal holds before they are folded into the metadata file.
+const CHECKPOINT_AFTER: usize = 1000;
+
+// All metadata in one JSON file, changes since it was last written are journaled next to it in `<path>.journal`.
+pub struct JsonStore {
+    path: PathBuf,
+    journal: Journal,
+}
+
+pub fn journal_path(path: &Path) -> PathBuf {
+    let mut journal = path.as_os_str().to_owned();
+    journal.push(".journal");
+
+    journal.into()
+}
+
+impl JsonStore {
+    pub fn open<T: Into<PathBuf>>(path: T) -> io::Result<JsonStore> {
+        let path = path.into();
+
+        Ok(JsonStore {
+            journal: Journal::open(&journal_path(&path))?,
+            path,
+        })
+    }
+}
+
+impl MetadataStore for JsonStore {
+    fn load(&mut self) -> io::Result<Inner> {
+        let mut inner: Inner = if self.path.exists() {
+            let file = File::open(&self.path)?;
+
+            serde_json::from_reader(file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        } else {
+            Inner::default()
+        };
+
+        // A crash during a checkpoint leaves changes in the journal that the file already includes.
+        for (sequence, change) in Journal::read::<Change>(&journal_path(&self.path))? {
+            if sequence > inner.sequence {
+                inner.replay(change);
+                inner.sequence = sequence;
+            }
+        }
+
+        // Starting from an empty journal also drops a line torn by a crash.
+        self.checkpoint(&inner)?;
+
+        Ok(inner)
+    }
+
+    fn save(&mut self, change: &Change, inner: &Inner) -> io::Result<()> {
+        self.journal.append(inner.sequence, change)?;
+
+        // The change is durable already, a failed checkpoint is retried with the next change.
+        if self.journal.len() >= CHECKPOINT_AFTER {
+            let _ = self.checkpoint(inner);
+        }
+
+        Ok(())
+    }
+
+    // Writes everything the journal holds into the metadata file, then starts an empty journal.
+    fn checkpoint(&mut self, inner: &Inner) -> io::Result<()> {
+        write_atomically(&self.path, &serde_json::to_vec(inner)?)?;
+
+        self.journal.clear()
+    }
+
+    fn import(&mut self, inner: &Inner) -> io::Result<()> {
+        self.checkpoint(inner)
+    }
+}
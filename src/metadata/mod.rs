@@ -0,0 +1,884 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use rocket::http::Status;
+use rocket::serde::Serialize;
+use serde::Deserialize;
+
+use crate::responses::{HandlerError, HttpResult};
+use crate::types::{Checksums, Dependent, ExtensionDescriptor, ExtensionIdentifier, ExtensionParent, ExtensionUpdate, LatestVersion, SnapshotBuild, VersionType};
+use crate::version::{compare_versions, Version, VersionRange};
+
+pub mod json;
+pub mod sqlite;
+
+use json::JsonStore;
+
+// Clones share the metadata, so a clone can be handed to a blocking worker.
+#[derive(Clone)]
+pub struct MetadataHandler {
+    inner: Arc<Mutex<Inner>>,
+    // None for a handler that only lives in memory. Always locked after `inner`.
+    store: Option<Arc<Mutex<Box<dyn MetadataStore>>>>,
+    // Downloads counted since the last `flush_downloads`.
+    downloads: Arc<Mutex<HashMap<ExtensionIdentifier, u32>>>,
+}
+
+// Where the metadata is kept. The handler serves everything from memory and hands each change to the store before it responds.
+pub trait MetadataStore: Send {
+    // Everything stored, read once when the handler is created.
+    fn load(&mut self) -> io::Result<Inner>;
+
+    // Makes a change durable, `inner` already has it applied.
+    fn save(&mut self, change: &Change, inner: &Inner) -> io::Result<()>;
+
+    // Called on shutdown, for stores that write some changes lazily.
+    fn checkpoint(&mut self, inner: &Inner) -> io::Result<()>;
+
+    // Replaces everything stored with `inner`.
+    fn import(&mut self, inner: &Inner) -> io::Result<()>;
+}
+
+// Copies everything in one store into another, returns the number of extensions copied.
+pub fn migrate(from: &mut dyn MetadataStore, to: &mut dyn MetadataStore) -> io::Result<usize> {
+    let inner = from.load()?;
+    to.import(&inner)?;
+
+    Ok(inner.versions.len())
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Inner {
+    pub downloads: HashMap<String, u32>,
+    pub latest: HashMap<String, LatestVersion>,
+    pub versions: HashMap<String, Vec<String>>,
+    // Keyed by `group:name:version`, then by published file name.
+    #[serde(default)]
+    pub checksums: HashMap<String, HashMap<String, Checksums>>,
+    #[serde(default)]
+    pub yanked: HashMap<String, Vec<String>>,
+    // Builds of snapshot versions keyed by `group:name:version`, oldest first.
+    #[serde(default)]
+    pub snapshots: HashMap<String, Vec<SnapshotBuild>>,
+    // Reverse index of ERM parents, keyed by the `group:name` of the parent and sorted by dependent.
+    #[serde(default)]
+    pub dependents: HashMap<String, Vec<Dependent>>,
+    // Sequence number of the last journaled change this state includes.
+    #[serde(default)]
+    pub sequence: u64,
+}
+
+// A single change to the metadata as it is handed to a store, replaying it makes the same change again.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Change {
+    Download {
+        id: ExtensionIdentifier,
+        // Downloads are saved in batches, changes journaled before they were count one download each.
+        #[serde(default = "one")]
+        count: u32,
+    },
+    NewVersion {
+        id: ExtensionIdentifier,
+        version: String,
+        checksums: HashMap<String, Checksums>,
+    },
+    NewSnapshotBuild {
+        id: ExtensionIdentifier,
+        version: String,
+        build: SnapshotBuild,
+        retention: usize,
+    },
+    Yank {
+        id: ExtensionIdentifier,
+        version: String,
+    },
+    Unyank {
+        id: ExtensionIdentifier,
+        version: String,
+    },
+    DeleteVersion {
+        id: ExtensionIdentifier,
+        version: String,
+    },
+    IndexParents {
+        id: ExtensionIdentifier,
+        version: String,
+        parents: Vec<ExtensionParent>,
+    },
+    Restore {
+        state: VersionState,
+    },
+}
+
+fn one() -> u32 {
+    1
+}
+
+impl Change {
+    // The extension the change is about.
+    pub fn id(&self) -> &ExtensionIdentifier {
+        match self {
+            Change::Download { id, .. }
+            | Change::NewVersion { id, .. }
+            | Change::NewSnapshotBuild { id, .. }
+            | Change::Yank { id, .. }
+            | Change::Unyank { id, .. }
+            | Change::DeleteVersion { id, .. }
+            | Change::IndexParents { id, .. } => id,
+            Change::Restore { state } => &state.id,
+        }
+    }
+}
+
+impl Inner {
+    fn is_yanked(&self, id: &ExtensionIdentifier, version: &str) -> bool {
+        self.yanked.get(&id.as_key())
+            .is_some_and(|yanked| yanked.iter().any(|it| it == version))
+    }
+
+    fn require_version(&self, id: &ExtensionIdentifier, version: &str) -> HttpResult<()> {
+        let known = self.versions.get(&id.as_key())
+            .is_some_and(|versions| versions.iter().any(|it| it == version));
+
+        if !known {
+            return Err(HandlerError::new(
+                "Version not found".into(),
+                Some(format!("{}:{} has not been published.", id.as_key(), version)),
+                Status::NotFound,
+            ));
+        }
+
+        Ok(())
+    }
+
+    // A change that failed when it was made fails the same way again, changes fail before they modify anything.
+    fn replay(&mut self, change: Change) {
+        let _ = match change {
+            Change::Download { id, count } => {
+                self.increment_download(&id, count);
+                Ok(())
+            }
+            Change::NewVersion { id, version, checksums } => self.add_version(&id, version, checksums),
+            Change::NewSnapshotBuild { id, version, build, retention } => self.add_snapshot_build(&id, version, build, retention).map(|_| ()),
+            Change::Yank { id, version } => self.yank(&id, &version),
+            Change::Unyank { id, version } => self.unyank(&id, &version),
+            Change::DeleteVersion { id, version } => self.delete_version(&id, &version).map(|_| ()),
+            Change::IndexParents { id, version, parents } => {
+                self.index_parents(&id, &version, &parents);
+                Ok(())
+            }
+            Change::Restore { state } => {
+                self.restore(state);
+                Ok(())
+            }
+        };
+    }
+
+    fn increment_download(&mut self, id: &ExtensionIdentifier, count: u32) {
+        *self.downloads.entry(id.as_key()).or_insert(0) += count;
+    }
+
+    fn remove_dependent(&mut self, descriptor: &str) {
+        for dependents in self.dependents.values_mut() {
+            dependents.retain(|it| it.descriptor != descriptor);
+        }
+        self.dependents.retain(|_, dependents| !dependents.is_empty());
+    }
+
+    fn add_version(
+        &mut self,
+        id: &ExtensionIdentifier,
+        version: String,
+        checksums: HashMap<String, Checksums>,
+    ) -> HttpResult<()> {
+        VersionType::classify(&version)?;
+
+        self.checksums.insert(version_key(id, &version), checksums);
+
+        let versions = self.versions.entry(id.as_key()).or_default();
+        if !versions.contains(&version) {
+            versions.push(version);
+        }
+
+        self.update_latest(id);
+
+        Ok(())
+    }
+
+    fn add_snapshot_build(
+        &mut self,
+        id: &ExtensionIdentifier,
+        version: String,
+        build: SnapshotBuild,
+        retention: usize,
+    ) -> HttpResult<Vec<SnapshotBuild>> {
+        self.add_version(id, version.clone(), build.checksums.clone())?;
+
+        let builds = self.snapshots.entry(version_key(id, &version)).or_default();
+        builds.push(build);
+
+        let expired = builds.len().saturating_sub(retention.max(1));
+
+        Ok(builds.drain(..expired).collect())
+    }
+
+    fn yank(&mut self, id: &ExtensionIdentifier, version: &str) -> HttpResult<()> {
+        self.require_version(id, version)?;
+
+        let yanked = self.yanked.entry(id.as_key()).or_default();
+        if !yanked.iter().any(|it| it == version) {
+            yanked.push(version.to_string());
+        }
+
+        self.update_latest(id);
+
+        Ok(())
+    }
+
+    fn unyank(&mut self, id: &ExtensionIdentifier, version: &str) -> HttpResult<()> {
+        self.require_version(id, version)?;
+
+        if let Some(yanked) = self.yanked.get_mut(&id.as_key()) {
+            yanked.retain(|it| it != version);
+        }
+
+        self.update_latest(id);
+
+        Ok(())
+    }
+
+    fn delete_version(&mut self, id: &ExtensionIdentifier, version: &str) -> HttpResult<bool> {
+        self.require_version(id, version)?;
+
+        self.checksums.remove(&version_key(id, version));
+        self.snapshots.remove(&version_key(id, version));
+        self.remove_dependent(&version_key(id, version));
+        if let Some(yanked) = self.yanked.get_mut(&id.as_key()) {
+            yanked.retain(|it| it != version);
+        }
+
+        let versions = self.versions.get_mut(&id.as_key()).unwrap();
+        versions.retain(|it| it != version);
+
+        if versions.is_empty() {
+            self.versions.remove(&id.as_key());
+            self.latest.remove(&id.as_key());
+            self.yanked.remove(&id.as_key());
+
+            return Ok(true);
+        }
+
+        self.update_latest(id);
+
+        Ok(false)
+    }
+
+    fn restore(&mut self, state: VersionState) {
+        let key = version_key(&state.id, &state.version);
+
+        match state.checksums {
+            Some(checksums) => self.checksums.insert(key.clone(), checksums),
+            None => self.checksums.remove(&key),
+        };
+        match state.snapshots {
+            Some(snapshots) => self.snapshots.insert(key.clone(), snapshots),
+            None => self.snapshots.remove(&key),
+        };
+
+        self.remove_dependent(&key);
+        for (parent, dependent) in state.dependents {
+            let dependents = self.dependents.entry(parent).or_default();
+            let index = dependents.partition_point(|it| it.descriptor < dependent.descriptor);
+            dependents.insert(index, dependent);
+        }
+
+        let yanked = self.yanked.entry(state.id.as_key()).or_default();
+        yanked.retain(|it| *it != state.version);
+        if state.yanked {
+            yanked.push(state.version.clone());
+        }
+        if yanked.is_empty() {
+            self.yanked.remove(&state.id.as_key());
+        }
+
+        if state.published {
+            let versions = self.versions.entry(state.id.as_key()).or_default();
+            if !versions.contains(&state.version) {
+                versions.push(state.version.clone());
+            }
+        } else if let Some(versions) = self.versions.get_mut(&state.id.as_key()) {
+            versions.retain(|it| *it != state.version);
+            if versions.is_empty() {
+                self.versions.remove(&state.id.as_key());
+                self.latest.remove(&state.id.as_key());
+                self.yanked.remove(&state.id.as_key());
+                return;
+            }
+        }
+
+        self.update_latest(&state.id);
+    }
+
+    fn index_parents(&mut self, id: &ExtensionIdentifier, version: &str, parents: &[ExtensionParent]) {
+        let descriptor = version_key(id, version);
+
+        self.remove_dependent(&descriptor);
+        for parent in parents {
+            let dependents = self.dependents.entry(parent.to_descriptor().identifier().as_key()).or_default();
+            let dependent = Dependent {
+                descriptor: descriptor.clone(),
+                parent_version: parent.version.clone(),
+            };
+
+            let index = dependents.partition_point(|it| it.descriptor < dependent.descriptor);
+            dependents.insert(index, dependent);
+        }
+    }
+
+    // The latest version of each channel is the highest one that is not yanked, regardless of upload order.
+    fn update_latest(&mut self, id: &ExtensionIdentifier) {
+        let mut latest = LatestVersion::default();
+
+        for version in self.versions.get(&id.as_key()).into_iter().flatten() {
+            if self.is_yanked(id, version) {
+                continue;
+            }
+
+            let channel = match VersionType::classify(version) {
+                Ok(VersionType::Release) => &mut latest.release,
+                Ok(VersionType::Alpha) => &mut latest.alpha,
+                Ok(VersionType::Beta) => &mut latest.beta,
+                Ok(VersionType::ReleaseCandidate) => &mut latest.rc,
+                Ok(VersionType::Snapshot) => &mut latest.snapshot,
+                Err(_) => continue,
+            };
+
+            if channel.as_ref().is_none_or(|it| compare_versions(version, it).is_gt()) {
+                *channel = Some(version.clone());
+            }
+        }
+
+        self.latest.insert(id.as_key(), latest);
+    }
+}
+
+// What the metadata held about a version before a publish or delete, restoring it undoes the change.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VersionState {
+    id: ExtensionIdentifier,
+    version: String,
+    published: bool,
+    #[serde(default)]
+    yanked: bool,
+    checksums: Option<HashMap<String, Checksums>>,
+    snapshots: Option<Vec<SnapshotBuild>>,
+    // Keyed by the parent the version declared.
+    dependents: Vec<(String, Dependent)>,
+}
+
+fn version_key(id: &ExtensionIdentifier, version: &str) -> String {
+    format!("{}:{}", id.as_key(), version)
+}
+
+impl MetadataHandler {
+    #[allow(dead_code)]
+    pub fn new() -> MetadataHandler {
+        MetadataHandler {
+            inner: Arc::new(Mutex::new(Inner::default())),
+            store: None,
+            downloads: Default::default(),
+        }
+    }
+
+    pub fn open(mut store: Box<dyn MetadataStore>) -> Result<MetadataHandler, io::Error> {
+        let mut inner = store.load()?;
+        // The stored latest versions may predate how they are picked now, they are picked again from the versions.
+        let ids: Vec<ExtensionIdentifier> = inner.versions.keys()
+            .filter_map(|key| key.split_once(':'))
+            .map(|(group, name)| ExtensionIdentifier {
+                group: group.to_string(),
+                name: name.to_string(),
+            })
+            .collect();
+        for id in &ids {
+            inner.update_latest(id);
+        }
+
+        Ok(MetadataHandler {
+            inner: Arc::new(Mutex::new(inner)),
+            store: Some(Arc::new(Mutex::new(store))),
+            downloads: Default::default(),
+        })
+    }
+
+    // Metadata kept in a JSON file, see `JsonStore`.
+    pub fn hydrate_cache<T: Into<PathBuf>>(path: T) -> Result<MetadataHandler, io::Error> {
+        Self::open(Box::new(JsonStore::open(path)?))
+    }
+
+    pub fn checkpoint(&self) -> io::Result<()> {
+        let inner = self.inner.lock().unwrap();
+
+        match &self.store {
+            Some(store) => store.lock().unwrap().checkpoint(&inner),
+            None => Ok(()),
+        }
+    }
+
+    // Applies the change and saves it before returning, both under the lock so the store sees changes in the order they were applied.
+    fn commit<R>(&self, change: Change, apply: impl FnOnce(&mut Inner) -> HttpResult<R>) -> HttpResult<R> {
+        let mut inner = self.inner.lock().unwrap();
+
+        let Some(store) = &self.store else {
+            return apply(&mut inner);
+        };
+        let mut store = store.lock().unwrap();
+
+        // What is served never gets ahead of what is stored, a change that could not be saved is forgotten again.
+        let previous = inner.clone();
+
+        // Changes fail before they modify anything, so one that failed has nothing to store.
+        let result = apply(&mut inner)?;
+        inner.sequence += 1;
+
+        if let Err(e) = store.save(&change, &inner) {
+            *inner = previous;
+
+            return Err(e.into());
+        }
+
+        Ok(result)
+    }
+
+    // Only counted in memory, downloads are saved in batches by `flush_downloads` so serving a file never waits on the store.
+    pub fn increment_download(&self, id: ExtensionIdentifier) {
+        *self.downloads.lock().unwrap().entry(id).or_insert(0) += 1;
+    }
+
+    // Saves the downloads counted since the last flush, one change per extension. Run periodically and on shutdown,
+    // downloads counted after the last flush before a crash are lost.
+    pub fn flush_downloads(&self) -> HttpResult<()> {
+        let downloads = std::mem::take(&mut *self.downloads.lock().unwrap());
+
+        let mut result = Ok(());
+        for (id, count) in downloads {
+            let saved = self.commit(Change::Download { id: id.clone(), count }, |inner| {
+                inner.increment_download(&id, count);
+                Ok(())
+            });
+
+            // Kept for the next flush when they could not be saved.
+            if let Err(e) = saved {
+                *self.downloads.lock().unwrap().entry(id).or_insert(0) += count;
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+
+    pub fn new_version(
+        &self,
+        id: ExtensionIdentifier,
+        version: String,
+        checksums: HashMap<String, Checksums>,
+    ) -> HttpResult<()> {
+        self.commit(Change::NewVersion {
+            id: id.clone(),
+            version: version.clone(),
+            checksums: checksums.clone(),
+        }, |inner| inner.add_version(&id, version, checksums))
+    }
+
+    pub fn next_build_number(&self, id: &ExtensionIdentifier, version: &str) -> u32 {
+        let inner = self.inner.lock().unwrap();
+
+        inner.snapshots.get(&version_key(id, version))
+            .and_then(|builds| builds.last())
+            .map_or(1, |it| it.build_number + 1)
+    }
+
+    // Records a new build of a snapshot version, the version itself reports the checksums of its newest build.
+    // Builds beyond the retention are forgotten and returned so their files can be removed.
+    pub fn new_snapshot_build(
+        &self,
+        id: ExtensionIdentifier,
+        version: String,
+        build: SnapshotBuild,
+        retention: usize,
+    ) -> HttpResult<Vec<SnapshotBuild>> {
+        self.commit(Change::NewSnapshotBuild {
+            id: id.clone(),
+            version: version.clone(),
+            build: build.clone(),
+            retention,
+        }, |inner| inner.add_snapshot_build(&id, version, build, retention))
+    }
+
+    pub fn snapshot_builds(&self, id: &ExtensionIdentifier, version: &str) -> Vec<SnapshotBuild> {
+        let inner = self.inner.lock().unwrap();
+
+        inner.snapshots.get(&version_key(id, version)).cloned().unwrap_or_default()
+    }
+
+    pub fn latest_snapshot_build(&self, id: &ExtensionIdentifier, version: &str) -> Option<String> {
+        let inner = self.inner.lock().unwrap();
+
+        inner.snapshots.get(&version_key(id, version))
+            .and_then(|builds| builds.last())
+            .map(|it| it.version.clone())
+    }
+
+    // A yanked version stays downloadable for anyone pinned to it, but is never reported as the latest.
+    pub fn yank(&self, id: &ExtensionIdentifier, version: &str) -> HttpResult<()> {
+        self.commit(Change::Yank {
+            id: id.clone(),
+            version: version.to_string(),
+        }, |inner| inner.yank(id, version))
+    }
+
+    pub fn unyank(&self, id: &ExtensionIdentifier, version: &str) -> HttpResult<()> {
+        self.commit(Change::Unyank {
+            id: id.clone(),
+            version: version.to_string(),
+        }, |inner| inner.unyank(id, version))
+    }
+
+    // Forgets the version entirely, returns true when it was the last version of the extension.
+    pub fn delete_version(&self, id: &ExtensionIdentifier, version: &str) -> HttpResult<bool> {
+        self.commit(Change::DeleteVersion {
+            id: id.clone(),
+            version: version.to_string(),
+        }, |inner| inner.delete_version(id, version))
+    }
+
+    pub fn version_state(&self, id: &ExtensionIdentifier, version: &str) -> VersionState {
+        let inner = self.inner.lock().unwrap();
+        let key = version_key(id, version);
+
+        VersionState {
+            id: id.clone(),
+            version: version.to_string(),
+            published: inner.versions.get(&id.as_key()).is_some_and(|it| it.iter().any(|it| it == version)),
+            yanked: inner.is_yanked(id, version),
+            checksums: inner.checksums.get(&key).cloned(),
+            snapshots: inner.snapshots.get(&key).cloned(),
+            dependents: inner.dependents.iter()
+                .flat_map(|(parent, dependents)| dependents.iter()
+                    .filter(|it| it.descriptor == key)
+                    .map(|it| (parent.clone(), it.clone())))
+                .collect(),
+        }
+    }
+
+    pub fn restore(&self, state: VersionState) -> HttpResult<()> {
+        self.commit(Change::Restore { state: state.clone() }, |inner| {
+            inner.restore(state);
+            Ok(())
+        })
+    }
+
+    // Replaces what the version was indexed with before, a republished version may declare other parents.
+    pub fn index_parents(&self, id: &ExtensionIdentifier, version: &str, parents: &[ExtensionParent]) -> HttpResult<()> {
+        self.commit(Change::IndexParents {
+            id: id.clone(),
+            version: version.to_string(),
+            parents: parents.to_vec(),
+        }, |inner| {
+            inner.index_parents(id, version, parents);
+            Ok(())
+        })
+    }
+
+    pub fn dependents(&self, id: &ExtensionIdentifier) -> Vec<Dependent> {
+        let inner = self.inner.lock().unwrap();
+
+        inner.dependents.get(&id.as_key()).cloned().unwrap_or_default()
+    }
+
+    // The highest published version in the range that is not yanked, an exact version is found even if it is yanked.
+    pub fn resolve_range(&self, id: &ExtensionIdentifier, range: &VersionRange) -> Option<String> {
+        let inner = self.inner.lock().unwrap();
+
+        inner.versions.get(&id.as_key())?.iter()
+            .filter(|it| range.is_exact() || !inner.is_yanked(id, it))
+            .filter_map(|it| Version::parse(it).map(|version| (it, version)))
+            .filter(|(_, version)| range.matches(version))
+            .max_by(|(_, a), (_, b)| a.cmp(b))
+            .map(|(it, _)| it.clone())
+    }
+
+    // Checks every installed version under a single lock, so one request sees one consistent state of the repository.
+    pub fn check_updates(&self, installed: &[ExtensionDescriptor], channel: &VersionType) -> Vec<ExtensionUpdate> {
+        let inner = self.inner.lock().unwrap();
+
+        installed.iter().map(|descriptor| {
+            let id = descriptor.identifier();
+
+            let latest = inner.versions.get(&id.as_key()).into_iter().flatten()
+                .filter(|it| !inner.is_yanked(&id, it))
+                .filter(|it| VersionType::classify(*it).is_ok_and(|it| it.stability() >= channel.stability()))
+                .max_by(|a, b| compare_versions(a, b))
+                .cloned();
+
+            let mut download_paths = Vec::new();
+            if let Some(version) = &latest {
+                let directory = format!("/registry/{}/{}", id.path().to_str().unwrap(), version);
+                let files = inner.checksums.get(&version_key(&id, version)).into_iter().flat_map(|it| it.keys());
+
+                download_paths.extend(files.map(|file| format!("{}/{}", directory, file)));
+                download_paths.sort();
+            }
+
+            ExtensionUpdate {
+                installed: descriptor.as_key(),
+                update_available: latest.as_ref().is_some_and(|it| compare_versions(it, &descriptor.version).is_gt()),
+                latest,
+                yanked: inner.is_yanked(&id, &descriptor.version),
+                download_paths,
+            }
+        }).collect()
+    }
+
+    pub fn is_yanked(&self, id: &ExtensionIdentifier, version: &str) -> bool {
+        let inner = self.inner.lock().unwrap();
+
+        inner.is_yanked(id, version)
+    }
+
+    // Whether the extension has at least one version that is not yanked.
+    pub fn is_available(&self, id: &ExtensionIdentifier) -> bool {
+        let inner = self.inner.lock().unwrap();
+
+        inner.versions.get(&id.as_key())
+            .is_some_and(|versions| versions.iter().any(|it| !inner.is_yanked(id, it)))
+    }
+
+    // A published version that is written differently but is the same version, like `1.0` for `1.0.0`.
+    pub fn equivalent_version(&self, id: &ExtensionIdentifier, version: &str) -> Option<String> {
+        let inner = self.inner.lock().unwrap();
+        let parsed = Version::parse(version)?;
+
+        inner.versions.get(&id.as_key())?.iter()
+            .find(|it| *it != version && Version::parse(it).is_some_and(|it| it == parsed))
+            .cloned()
+    }
+
+    pub fn has_version(&self, id: &ExtensionIdentifier, version: &str) -> bool {
+        let inner = self.inner.lock().unwrap();
+
+        inner.versions.get(&id.as_key())
+            .is_some_and(|versions| versions.iter().any(|it| it == version))
+    }
+
+    pub fn get_managed_metadata(
+        &self,
+        identifier: &ExtensionIdentifier,
+    ) -> HttpResult<(u32, LatestVersion, Vec<String>)> {
+        let inner = self.inner.lock().unwrap();
+
+        let mut versions = inner.versions.get(&identifier.as_key()).unwrap_or(&Vec::new()).clone();
+        versions.sort_by(|a, b| compare_versions(a, b));
+
+        Ok((
+            *inner.downloads.get(&identifier.as_key()).unwrap_or(&0)
+                + self.downloads.lock().unwrap().get(identifier).unwrap_or(&0),
+            inner.latest.get(&identifier.as_key()).unwrap_or(&Default::default()).clone(),
+            versions
+        ))
+    }
+
+    pub fn checksums(
+        &self,
+        identifier: &ExtensionIdentifier,
+        version: &str,
+    ) -> HashMap<String, Checksums> {
+        let inner = self.inner.lock().unwrap();
+
+        inner.checksums.get(&version_key(identifier, version)).cloned().unwrap_or_default()
+    }
+
+    pub fn extension_count(&self) -> u32 {
+        let inner = self.inner.lock().unwrap();
+
+
+        inner.versions.keys().len() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io;
+
+    use crate::metadata::json::journal_path;
+    use crate::metadata::{Change, Inner, MetadataHandler, MetadataStore};
+    use crate::types::{ExtensionIdentifier, SnapshotBuild};
+
+    #[test]
+    fn test_journal_replay() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("metadata.json");
+        let id = ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "journaled".into(),
+        };
+
+        // Dropped without a checkpoint, like a server that was killed.
+        let handler = MetadataHandler::hydrate_cache(&path).unwrap();
+        handler.new_version(id.clone(), "1.0".into(), Default::default()).unwrap();
+        handler.new_version(id.clone(), "1.1".into(), Default::default()).unwrap();
+        handler.yank(&id, "1.1").unwrap();
+        handler.increment_download(id.clone());
+        handler.increment_download(id.clone());
+        handler.flush_downloads().unwrap();
+        // A change that fails is not journaled.
+        let journal = fs::read(journal_path(&path)).unwrap();
+        assert!(handler.yank(&id, "2.0").is_err());
+        assert_eq!(fs::read(journal_path(&path)).unwrap(), journal);
+        drop(handler);
+
+        let handler = MetadataHandler::hydrate_cache(&path).unwrap();
+        let (downloads, latest, versions) = handler.get_managed_metadata(&id).unwrap();
+        assert_eq!(downloads, 2);
+        assert_eq!(latest.release, Some("1.0".to_string()));
+        assert_eq!(versions, vec!["1.0", "1.1"]);
+        assert!(handler.is_yanked(&id, "1.1"));
+        drop(handler);
+
+        // A crash between writing the file and clearing the journal leaves changes the file includes already.
+        fs::write(journal_path(&path), journal).unwrap();
+        let handler = MetadataHandler::hydrate_cache(&path).unwrap();
+        let (downloads, _, _) = handler.get_managed_metadata(&id).unwrap();
+        assert_eq!(downloads, 2);
+        assert_eq!(fs::read(journal_path(&path)).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_unsaved_change_is_forgotten() {
+        struct FailingStore;
+
+        impl MetadataStore for FailingStore {
+            fn load(&mut self) -> io::Result<Inner> {
+                Ok(Inner::default())
+            }
+
+            fn save(&mut self, _change: &Change, _inner: &Inner) -> io::Result<()> {
+                Err(io::Error::other("disk full"))
+            }
+
+            fn checkpoint(&mut self, _inner: &Inner) -> io::Result<()> {
+                Ok(())
+            }
+
+            fn import(&mut self, _inner: &Inner) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let handler = MetadataHandler::open(Box::new(FailingStore)).unwrap();
+        let id = ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "unsaved".into(),
+        };
+
+        assert!(handler.new_version(id.clone(), "1.0".into(), Default::default()).is_err());
+        assert!(!handler.has_version(&id, "1.0"));
+        assert_eq!(handler.inner.lock().unwrap().sequence, 0);
+    }
+
+    #[test]
+    fn test_latest_is_picked_on_load() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("metadata.json");
+        fs::write(&path, r#"{
+            "downloads": {},
+            "latest": { "com.example:stale": { "release": "1.0" } },
+            "versions": { "com.example:stale": ["1.0", "1.1"] }
+        }"#).unwrap();
+
+        let handler = MetadataHandler::hydrate_cache(&path).unwrap();
+        let (_, latest, _) = handler.get_managed_metadata(&ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "stale".into(),
+        }).unwrap();
+        assert_eq!(latest.release, Some("1.1".to_string()));
+    }
+
+    #[test]
+    fn test_latest_is_highest_version() {
+        let handler = MetadataHandler::new();
+        let id = ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "ordering".into(),
+        };
+
+        for version in ["2.0.0", "1.0.1", "2.1.0-beta", "2.0.1-beta", "10.0.0-rc"] {
+            handler.new_version(id.clone(), version.into(), Default::default()).unwrap();
+        }
+
+        let (_, latest, versions) = handler.get_managed_metadata(&id).unwrap();
+        assert_eq!(latest.release, Some("2.0.0".to_string()));
+        assert_eq!(latest.beta, Some("2.1.0-beta".to_string()));
+        assert_eq!(latest.rc, Some("10.0.0-rc".to_string()));
+        assert_eq!(versions, vec!["1.0.1", "2.0.0", "2.0.1-beta", "2.1.0-beta", "10.0.0-rc"]);
+
+        handler.yank(&id, "2.0.0").unwrap();
+        let (_, latest, _) = handler.get_managed_metadata(&id).unwrap();
+        assert_eq!(latest.release, Some("1.0.1".to_string()));
+    }
+
+    #[test]
+    fn test_restore_deleted_version() {
+        let handler = MetadataHandler::new();
+        let id = ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "restored".into(),
+        };
+
+        handler.new_version(id.clone(), "1.0".into(), Default::default()).unwrap();
+        handler.yank(&id, "1.0").unwrap();
+        let previous = handler.version_state(&id, "1.0");
+        assert!(handler.delete_version(&id, "1.0").unwrap());
+        assert!(!handler.has_version(&id, "1.0"));
+
+        // A delete that is rolled back brings the version back as it was, yanked included.
+        handler.restore(previous).unwrap();
+        let (_, latest, versions) = handler.get_managed_metadata(&id).unwrap();
+        assert_eq!(versions, vec!["1.0"]);
+        assert_eq!(latest.release, None);
+        assert!(handler.is_yanked(&id, "1.0"));
+    }
+
+    #[test]
+    fn test_snapshot_retention() {
+        let handler = MetadataHandler::new();
+        let id = ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "retention".into(),
+        };
+
+        let mut expired = Vec::new();
+        for _ in 0..3 {
+            let build_number = handler.next_build_number(&id, "1.0-SNAPSHOT");
+            expired.extend(handler.new_snapshot_build(id.clone(), "1.0-SNAPSHOT".into(), SnapshotBuild {
+                version: format!("1.0-20261017.120000-{}", build_number),
+                timestamp: "20261017.120000".into(),
+                build_number,
+                checksums: Default::default(),
+            }, 2).unwrap());
+        }
+
+        let builds: Vec<u32> = handler.snapshot_builds(&id, "1.0-SNAPSHOT").iter().map(|it| it.build_number).collect();
+        assert_eq!(builds, vec![2, 3]);
+        assert_eq!(expired.iter().map(|it| it.build_number).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(handler.latest_snapshot_build(&id, "1.0-SNAPSHOT"), Some("1.0-20261017.120000-3".to_string()));
+
+        let (_, latest, versions) = handler.get_managed_metadata(&id).unwrap();
+        assert_eq!(latest.snapshot, Some("1.0-SNAPSHOT".to_string()));
+        assert_eq!(versions, vec!["1.0-SNAPSHOT"]);
+    }
+}
@@ -0,0 +1,491 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs::create_dir_all;
+use std::io;
+use std::path::PathBuf;
+
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Transaction};
+
+use crate::metadata::{Change, Inner, MetadataStore};
+use crate::types::{Checksums, Dependent, LatestVersion, SnapshotBuild};
+
+// Every table is keyed by the `group:name` of the extension its rows belong to.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS downloads (
+        extension TEXT PRIMARY KEY,
+        count INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS latest (
+        extension TEXT PRIMARY KEY,
+        release TEXT,
+        alpha TEXT,
+        beta TEXT,
+        rc TEXT,
+        snapshot TEXT
+    );
+    CREATE TABLE IF NOT EXISTS versions (
+        extension TEXT NOT NULL,
+        version TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (extension, version)
+    );
+    CREATE TABLE IF NOT EXISTS yanked (
+        extension TEXT NOT NULL,
+        version TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        PRIMARY KEY (extension, version)
+    );
+    CREATE TABLE IF NOT EXISTS checksums (
+        extension TEXT NOT NULL,
+        version TEXT NOT NULL,
+        file TEXT NOT NULL,
+        sha256 TEXT NOT NULL,
+        sha1 TEXT NOT NULL,
+        md5 TEXT NOT NULL,
+        PRIMARY KEY (extension, version, file)
+    );
+    CREATE TABLE IF NOT EXISTS snapshot_builds (
+        extension TEXT NOT NULL,
+        version TEXT NOT NULL,
+        build_number INTEGER NOT NULL,
+        build_version TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        checksums TEXT NOT NULL,
+        PRIMARY KEY (extension, version, build_number)
+    );
+    CREATE TABLE IF NOT EXISTS dependents (
+        extension TEXT NOT NULL,
+        parent TEXT NOT NULL,
+        descriptor TEXT NOT NULL,
+        parent_version TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS dependents_by_parent ON dependents (parent);
+    CREATE INDEX IF NOT EXISTS dependents_by_extension ON dependents (extension);
+    CREATE INDEX IF NOT EXISTS dependents_by_descriptor ON dependents (descriptor);
+";
+
+const TABLES: [&str; 7] = ["downloads", "latest", "versions", "yanked", "checksums", "snapshot_builds", "dependents"];
+
+// Metadata in an SQLite database. Every change is written in its own transaction and only touches the rows of the
+// version it is about, and the latest versions of its extension.
+// Like the JSON store it is only read on startup, lookups are served from the handler's memory for either store. The
+// tables are indexed for queries by other tools, the server itself does not query them.
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+fn to_io(e: rusqlite::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+// The `group:name` of a `group:name:version` descriptor.
+fn extension_of(descriptor: &str) -> Option<&str> {
+    let (group, rest) = descriptor.split_once(':')?;
+    let (name, _) = rest.split_once(':')?;
+
+    Some(&descriptor[..group.len() + 1 + name.len()])
+}
+
+impl SqliteStore {
+    pub fn open<T: Into<PathBuf>>(path: T) -> io::Result<SqliteStore> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        let connection = Connection::open(path).map_err(to_io)?;
+        connection.execute_batch(SCHEMA).map_err(to_io)?;
+
+        Ok(SqliteStore {
+            connection,
+        })
+    }
+
+    fn read(&self) -> rusqlite::Result<Inner> {
+        let mut inner = Inner::default();
+
+        let mut statement = self.connection.prepare("SELECT extension, count FROM downloads")?;
+        for row in statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+            let (extension, count) = row?;
+            inner.downloads.insert(extension, count);
+        }
+
+        let mut statement = self.connection.prepare("SELECT extension, release, alpha, beta, rc, snapshot FROM latest")?;
+        for row in statement.query_map([], |row| Ok((row.get::<_, String>(0)?, LatestVersion {
+            release: row.get(1)?,
+            alpha: row.get(2)?,
+            beta: row.get(3)?,
+            rc: row.get(4)?,
+            snapshot: row.get(5)?,
+        })))? {
+            let (extension, latest) = row?;
+            inner.latest.insert(extension, latest);
+        }
+
+        for (table, versions) in [("versions", &mut inner.versions), ("yanked", &mut inner.yanked)] {
+            let mut statement = self.connection.prepare(&format!("SELECT extension, version FROM {} ORDER BY extension, position", table))?;
+            for row in statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))? {
+                let (extension, version) = row?;
+                versions.entry(extension).or_default().push(version);
+            }
+        }
+
+        let mut statement = self.connection.prepare("SELECT extension, version, file, sha256, sha1, md5 FROM checksums")?;
+        for row in statement.query_map([], |row| Ok((
+            format!("{}:{}", row.get::<_, String>(0)?, row.get::<_, String>(1)?),
+            row.get::<_, String>(2)?,
+            Checksums {
+                sha256: row.get(3)?,
+                sha1: row.get(4)?,
+                md5: row.get(5)?,
+            },
+        )))? {
+            let (key, file, checksums) = row?;
+            inner.checksums.entry(key).or_default().insert(file, checksums);
+        }
+
+        let mut statement = self.connection.prepare(
+            "SELECT extension, version, build_number, build_version, timestamp, checksums FROM snapshot_builds ORDER BY extension, version, build_number"
+        )?;
+        for row in statement.query_map([], |row| {
+            let checksums: String = row.get(5)?;
+
+            Ok((
+                format!("{}:{}", row.get::<_, String>(0)?, row.get::<_, String>(1)?),
+                SnapshotBuild {
+                    version: row.get(3)?,
+                    timestamp: row.get(4)?,
+                    build_number: row.get(2)?,
+                    checksums: serde_json::from_str(&checksums)
+                        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(5, Type::Text, Box::new(e)))?,
+                },
+            ))
+        })? {
+            let (key, build) = row?;
+            inner.snapshots.entry(key).or_default().push(build);
+        }
+
+        let mut statement = self.connection.prepare("SELECT parent, descriptor, parent_version FROM dependents ORDER BY parent, descriptor")?;
+        for row in statement.query_map([], |row| Ok((row.get::<_, String>(0)?, Dependent {
+            descriptor: row.get(1)?,
+            parent_version: row.get(2)?,
+        })))? {
+            let (parent, dependent) = row?;
+            inner.dependents.entry(parent).or_default().push(dependent);
+        }
+
+        Ok(inner)
+    }
+
+    fn write(&mut self, change: &Change, inner: &Inner) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        let extension = change.id().as_key();
+
+        let version = match change {
+            // Downloads are by far the most frequent change and touch nothing else.
+            Change::Download { .. } => {
+                transaction.execute(
+                    "INSERT INTO downloads (extension, count) VALUES (?1, ?2) ON CONFLICT (extension) DO UPDATE SET count = excluded.count",
+                    params![extension, inner.downloads.get(&extension).copied().unwrap_or(0)],
+                )?;
+
+                return transaction.commit();
+            }
+            Change::NewVersion { version, .. }
+            | Change::NewSnapshotBuild { version, .. }
+            | Change::Yank { version, .. }
+            | Change::Unyank { version, .. }
+            | Change::DeleteVersion { version, .. }
+            | Change::IndexParents { version, .. } => version,
+            Change::Restore { state } => &state.version,
+        };
+
+        write_version(&transaction, inner, &extension, version)?;
+        write_latest(&transaction, inner, &extension)?;
+
+        transaction.commit()
+    }
+
+    fn replace(&mut self, inner: &Inner) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+
+        for table in TABLES {
+            transaction.execute(&format!("DELETE FROM {}", table), [])?;
+        }
+
+        let mut dependents: HashMap<&str, Vec<(&String, &Dependent)>> = HashMap::new();
+        for (parent, list) in &inner.dependents {
+            for dependent in list {
+                if let Some(extension) = extension_of(&dependent.descriptor) {
+                    dependents.entry(extension).or_default().push((parent, dependent));
+                }
+            }
+        }
+
+        let extensions: BTreeSet<&String> = inner.downloads.keys()
+            .chain(inner.latest.keys())
+            .chain(inner.versions.keys())
+            .collect();
+        for extension in extensions {
+            let dependents = dependents.get(extension.as_str()).map(Vec::as_slice).unwrap_or_default();
+            insert_extension(&transaction, inner, extension, dependents)?;
+        }
+
+        transaction.commit()
+    }
+
+    #[cfg(test)]
+    fn count(&self, table: &str) -> usize {
+        self.connection.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+}
+
+// Brings the rows of a single version in line with `inner`, the rows of other versions are left as they are.
+fn write_version(transaction: &Transaction, inner: &Inner, extension: &str, version: &str) -> rusqlite::Result<()> {
+    let key = format!("{}:{}", extension, version);
+    let listed = |versions: &HashMap<String, Vec<String>>| versions.get(extension).is_some_and(|it| it.iter().any(|it| it == version));
+
+    // A version that is listed already keeps its position, a new one goes last like it does in memory.
+    for (table, present) in [("versions", listed(&inner.versions)), ("yanked", listed(&inner.yanked))] {
+        if present {
+            transaction.execute(&format!(
+                "INSERT INTO {0} (extension, version, position) SELECT ?1, ?2, COALESCE(MAX(position) + 1, 0) FROM {0} WHERE extension = ?1
+                 ON CONFLICT (extension, version) DO NOTHING",
+                table
+            ), params![extension, version])?;
+        } else {
+            transaction.execute(&format!("DELETE FROM {} WHERE extension = ?1 AND version = ?2", table), params![extension, version])?;
+        }
+    }
+
+    transaction.execute("DELETE FROM checksums WHERE extension = ?1 AND version = ?2", params![extension, version])?;
+    for (file, checksums) in inner.checksums.get(&key).into_iter().flatten() {
+        transaction.execute(
+            "INSERT INTO checksums (extension, version, file, sha256, sha1, md5) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![extension, version, file, checksums.sha256, checksums.sha1, checksums.md5],
+        )?;
+    }
+
+    // Expired builds are gone from memory, the builds that are left are few.
+    transaction.execute("DELETE FROM snapshot_builds WHERE extension = ?1 AND version = ?2", params![extension, version])?;
+    for build in inner.snapshots.get(&key).into_iter().flatten() {
+        insert_build(transaction, extension, version, build)?;
+    }
+
+    transaction.execute("DELETE FROM dependents WHERE descriptor = ?1", [&key])?;
+    for (parent, dependents) in &inner.dependents {
+        for dependent in dependents.iter().filter(|it| it.descriptor == key) {
+            transaction.execute(
+                "INSERT INTO dependents (extension, parent, descriptor, parent_version) VALUES (?1, ?2, ?3, ?4)",
+                params![extension, parent, dependent.descriptor, dependent.parent_version],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_latest(transaction: &Transaction, inner: &Inner, extension: &str) -> rusqlite::Result<()> {
+    match inner.latest.get(extension) {
+        Some(latest) => transaction.execute(
+            "INSERT INTO latest (extension, release, alpha, beta, rc, snapshot) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (extension) DO UPDATE SET release = excluded.release, alpha = excluded.alpha, beta = excluded.beta,
+             rc = excluded.rc, snapshot = excluded.snapshot",
+            params![extension, latest.release, latest.alpha, latest.beta, latest.rc, latest.snapshot],
+        )?,
+        None => transaction.execute("DELETE FROM latest WHERE extension = ?1", [extension])?,
+    };
+
+    Ok(())
+}
+
+fn insert_build(transaction: &Transaction, extension: &str, version: &str, build: &SnapshotBuild) -> rusqlite::Result<()> {
+    transaction.execute(
+        "INSERT INTO snapshot_builds (extension, version, build_number, build_version, timestamp, checksums) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            extension,
+            version,
+            build.build_number,
+            build.version,
+            build.timestamp,
+            serde_json::to_string(&build.checksums).unwrap(),
+        ],
+    )?;
+
+    Ok(())
+}
+
+fn insert_extension(
+    transaction: &Transaction,
+    inner: &Inner,
+    extension: &str,
+    dependents: &[(&String, &Dependent)],
+) -> rusqlite::Result<()> {
+    if let Some(count) = inner.downloads.get(extension) {
+        transaction.execute("INSERT INTO downloads (extension, count) VALUES (?1, ?2)", params![extension, count])?;
+    }
+
+    if let Some(latest) = inner.latest.get(extension) {
+        transaction.execute(
+            "INSERT INTO latest (extension, release, alpha, beta, rc, snapshot) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![extension, latest.release, latest.alpha, latest.beta, latest.rc, latest.snapshot],
+        )?;
+    }
+
+    for (position, version) in inner.yanked.get(extension).into_iter().flatten().enumerate() {
+        transaction.execute("INSERT INTO yanked (extension, version, position) VALUES (?1, ?2, ?3)", params![extension, version, position])?;
+    }
+
+    for (position, version) in inner.versions.get(extension).into_iter().flatten().enumerate() {
+        transaction.execute("INSERT INTO versions (extension, version, position) VALUES (?1, ?2, ?3)", params![extension, version, position])?;
+
+        let key = format!("{}:{}", extension, version);
+        for (file, checksums) in inner.checksums.get(&key).into_iter().flatten() {
+            transaction.execute(
+                "INSERT INTO checksums (extension, version, file, sha256, sha1, md5) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![extension, version, file, checksums.sha256, checksums.sha1, checksums.md5],
+            )?;
+        }
+
+        for build in inner.snapshots.get(&key).into_iter().flatten() {
+            insert_build(transaction, extension, version, build)?;
+        }
+    }
+
+    for (parent, dependent) in dependents {
+        transaction.execute(
+            "INSERT INTO dependents (extension, parent, descriptor, parent_version) VALUES (?1, ?2, ?3, ?4)",
+            params![extension, parent, dependent.descriptor, dependent.parent_version],
+        )?;
+    }
+
+    Ok(())
+}
+
+impl MetadataStore for SqliteStore {
+    fn load(&mut self) -> io::Result<Inner> {
+        self.read().map_err(to_io)
+    }
+
+    fn save(&mut self, change: &Change, inner: &Inner) -> io::Result<()> {
+        self.write(change, inner).map_err(to_io)
+    }
+
+    // Changes are written as they are made, there is nothing left to write.
+    fn checkpoint(&mut self, _inner: &Inner) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn import(&mut self, inner: &Inner) -> io::Result<()> {
+        self.replace(inner).map_err(to_io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::metadata::json::JsonStore;
+    use crate::metadata::sqlite::SqliteStore;
+    use crate::metadata::{migrate, MetadataHandler};
+    use crate::types::{Checksums, ExtensionIdentifier, ExtensionParent, SnapshotBuild};
+
+    fn populate(handler: &MetadataHandler) -> ExtensionIdentifier {
+        let id = ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "stored".into(),
+        };
+        let checksums = HashMap::from([("stored-1.0.jar".to_string(), Checksums {
+            sha256: "a".into(),
+            sha1: "b".into(),
+            md5: "c".into(),
+        })]);
+
+        handler.new_version(id.clone(), "1.0".into(), checksums).unwrap();
+        handler.new_version(id.clone(), "1.1".into(), Default::default()).unwrap();
+        handler.yank(&id, "1.1").unwrap();
+        handler.new_snapshot_build(id.clone(), "2.0-SNAPSHOT".into(), SnapshotBuild {
+            version: "2.0-20261017.120000-1".into(),
+            timestamp: "20261017.120000".into(),
+            build_number: 1,
+            checksums: Default::default(),
+        }, 10).unwrap();
+        handler.index_parents(&id, "1.0", &[ExtensionParent {
+            group: "com.example".into(),
+            extension: "core".into(),
+            version: "1.0".into(),
+        }]).unwrap();
+        handler.increment_download(id.clone());
+        handler.increment_download(id.clone());
+        handler.flush_downloads().unwrap();
+
+        id
+    }
+
+    fn assert_populated(handler: &MetadataHandler, id: &ExtensionIdentifier) {
+        let (downloads, latest, versions) = handler.get_managed_metadata(id).unwrap();
+        assert_eq!(downloads, 2);
+        assert_eq!(latest.release, Some("1.0".to_string()));
+        assert_eq!(latest.snapshot, Some("2.0-SNAPSHOT".to_string()));
+        assert_eq!(versions, vec!["1.0", "1.1", "2.0-SNAPSHOT"]);
+        assert!(handler.is_yanked(id, "1.1"));
+        assert_eq!(handler.checksums(id, "1.0")["stored-1.0.jar"].sha1, "b");
+        assert_eq!(handler.latest_snapshot_build(id, "2.0-SNAPSHOT"), Some("2.0-20261017.120000-1".to_string()));
+
+        let dependents = handler.dependents(&ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "core".into(),
+        });
+        assert_eq!(dependents.len(), 1);
+        assert_eq!(dependents[0].descriptor, "com.example:stored:1.0");
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("metadata.db");
+
+        let handler = MetadataHandler::open(Box::new(SqliteStore::open(&path).unwrap())).unwrap();
+        let id = populate(&handler);
+        drop(handler);
+
+        let handler = MetadataHandler::open(Box::new(SqliteStore::open(&path).unwrap())).unwrap();
+        assert_populated(&handler, &id);
+        handler.delete_version(&id, "2.0-SNAPSHOT").unwrap();
+        drop(handler);
+
+        // Deleting a version only leaves rows of what is still published.
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.count("snapshot_builds"), 0);
+        assert_eq!(store.count("versions"), 2);
+        assert_eq!(store.count("dependents"), 1);
+
+        // A version that is written again keeps its position, the rows of other versions are left as they are.
+        let handler = MetadataHandler::open(Box::new(store)).unwrap();
+        handler.unyank(&id, "1.1").unwrap();
+        handler.new_version(id.clone(), "1.0".into(), Default::default()).unwrap();
+        drop(handler);
+
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.count("yanked"), 0);
+        assert_eq!(store.count("checksums"), 0);
+        assert_eq!(store.count("dependents"), 1);
+        assert_eq!(store.read().unwrap().versions[&id.as_key()], vec!["1.0", "1.1"]);
+    }
+
+    #[test]
+    fn test_migrate() {
+        let directory = tempfile::tempdir().unwrap();
+        let json = directory.path().join("metadata.json");
+
+        let handler = MetadataHandler::hydrate_cache(&json).unwrap();
+        let id = populate(&handler);
+        drop(handler);
+
+        let mut sqlite = SqliteStore::open(directory.path().join("metadata.db")).unwrap();
+        assert_eq!(migrate(&mut JsonStore::open(&json).unwrap(), &mut sqlite).unwrap(), 1);
+
+        let handler = MetadataHandler::open(Box::new(sqlite)).unwrap();
+        assert_populated(&handler, &id);
+    }
+}
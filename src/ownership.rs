@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use rocket::http::Status;
+use serde::{Deserialize, Serialize};
+
+use crate::journal::write_atomically;
+use crate::responses::{HandlerError, HttpResult};
+
+// Principals owning each group id, persisted in data/owners.json. A group is owned by the first principal to publish
+// into it, only its owners may publish into it and the groups below it after that. The nearest owned group decides,
+// `com.example.tools` is covered by an owned `com.example` unless it is owned itself. Clones share the owners.
+#[derive(Clone)]
+pub struct OwnershipStore {
+    path: PathBuf,
+    groups: Arc<Mutex<BTreeMap<String, Vec<String>>>>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct OwnershipFile {
+    groups: BTreeMap<String, Vec<String>>,
+}
+
+// The nearest owned group at or above the group, `com.example` for `com.example.tools` but not for `com.examples`.
+fn owning<'a>(groups: &'a BTreeMap<String, Vec<String>>, group: &'a str) -> Option<(&'a str, &'a Vec<String>)> {
+    let mut group = group;
+    loop {
+        if let Some(owners) = groups.get(group) {
+            return Some((group, owners));
+        }
+        group = &group[..group.rfind('.')?];
+    }
+}
+
+fn not_an_owner(group: &str, owners: &[String]) -> HandlerError {
+    HandlerError::new(
+        "Group owned by another principal".into(),
+        Some(format!("'{}' is owned by {}.", group, owners.join(", "))),
+        Status::Forbidden,
+    )
+}
+
+impl OwnershipStore {
+    pub fn load<T: Into<PathBuf>>(path: T) -> Result<OwnershipStore, io::Error> {
+        let path = path.into();
+        let file = if Path::new(&path).exists() {
+            serde_json::from_reader(File::open(&path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        } else {
+            OwnershipFile::default()
+        };
+
+        Ok(OwnershipStore {
+            path,
+            groups: Arc::new(Mutex::new(file.groups)),
+        })
+    }
+
+    fn persist(&self, groups: &BTreeMap<String, Vec<String>>) -> io::Result<()> {
+        write_atomically(&self.path, &serde_json::to_vec(&OwnershipFile {
+            groups: groups.clone(),
+        })?)
+    }
+
+    // Changes a copy of the owners, which only replaces them once it is persisted.
+    fn update<R>(&self, change: impl FnOnce(&mut BTreeMap<String, Vec<String>>) -> HttpResult<R>) -> HttpResult<R> {
+        let mut groups = self.groups.lock().unwrap();
+
+        let mut changed = groups.clone();
+        let result = change(&mut changed)?;
+        self.persist(&changed)?;
+        *groups = changed;
+
+        Ok(result)
+    }
+
+    pub fn owners(&self, group: &str) -> Option<Vec<String>> {
+        self.groups.lock().unwrap().get(group).cloned()
+    }
+
+    // Whether the principal may publish into the group, which is the case for any principal while neither it nor a group
+    // above it is owned.
+    pub fn check(&self, group: &str, principal: &str) -> HttpResult<()> {
+        match owning(&self.groups.lock().unwrap(), group) {
+            Some((owned, owners)) if !owners.iter().any(|it| it == principal) => Err(not_an_owner(owned, owners)),
+            _ => Ok(()),
+        }
+    }
+
+    // Makes the principal the owner of the group if neither it nor a group above it is owned, returns true when this
+    // claimed it.
+    pub fn claim(&self, group: &str, principal: &str) -> HttpResult<bool> {
+        // Most publishes are into groups that are owned already, those are checked without writing anything.
+        if owning(&self.groups.lock().unwrap(), group).is_some() {
+            return self.check(group, principal).map(|_| false);
+        }
+
+        self.update(|groups| match owning(groups, group) {
+            Some((owned, owners)) if !owners.iter().any(|it| it == principal) => Err(not_an_owner(owned, owners)),
+            Some(_) => Ok(false),
+            None => {
+                groups.insert(group.to_string(), vec![principal.to_string()]);
+                Ok(true)
+            }
+        })
+    }
+
+    // Undoes a claim made by a publish that failed.
+    pub fn release(&self, group: &str) -> HttpResult<()> {
+        self.update(|groups| {
+            groups.remove(group);
+            Ok(())
+        })
+    }
+
+    // Adds an owner, the existing owners keep the group.
+    pub fn share(&self, group: &str, principal: &str) -> HttpResult<Vec<String>> {
+        self.update(|groups| {
+            let owners = groups.entry(group.to_string()).or_default();
+            if !owners.iter().any(|it| it == principal) {
+                owners.push(principal.to_string());
+            }
+
+            Ok(owners.clone())
+        })
+    }
+
+    // Makes the principal the only owner.
+    pub fn transfer(&self, group: &str, principal: &str) -> HttpResult<Vec<String>> {
+        self.update(|groups| {
+            let owners = vec![principal.to_string()];
+            groups.insert(group.to_string(), owners.clone());
+
+            Ok(owners)
+        })
+    }
+
+    // Returns false when the principal did not own the group. A group left without owners can be claimed again.
+    pub fn remove(&self, group: &str, principal: &str) -> HttpResult<bool> {
+        self.update(|groups| {
+            let Some(owners) = groups.get_mut(group) else {
+                return Ok(false);
+            };
+
+            let owned = owners.iter().any(|it| it == principal);
+            owners.retain(|it| it != principal);
+            if owners.is_empty() {
+                groups.remove(group);
+            }
+
+            Ok(owned)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ownership::OwnershipStore;
+
+    #[test]
+    fn test_ownership() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("owners.json");
+
+        let store = OwnershipStore::load(&path).unwrap();
+        assert!(store.claim("com.example", "team-a").unwrap());
+        assert!(!store.claim("com.example", "team-a").unwrap());
+        assert!(store.claim("com.example", "team-b").is_err());
+        assert!(store.check("com.example", "team-b").is_err());
+        // An owned group covers the groups below it, but not groups that only start with its name.
+        assert!(store.check("com.example.tools", "team-b").is_err());
+        assert!(!store.claim("com.example.tools", "team-a").unwrap());
+        assert_eq!(store.owners("com.example.tools"), None);
+        assert!(store.claim("com.examples", "team-b").unwrap());
+        // A group owned itself is decided by its own owners.
+        store.transfer("com.example.tools", "team-d").unwrap();
+        assert!(store.check("com.example.tools.cli", "team-d").is_ok());
+        assert!(store.check("com.example.tools", "team-a").is_err());
+
+        store.share("com.example", "team-b").unwrap();
+        assert!(store.check("com.example", "team-b").is_ok());
+
+        let store = OwnershipStore::load(&path).unwrap();
+        assert_eq!(store.transfer("com.example", "team-c").unwrap(), vec!["team-c".to_string()]);
+        assert!(store.check("com.example", "team-a").is_err());
+
+        assert!(store.remove("com.example", "team-c").unwrap());
+        assert_eq!(store.owners("com.example"), None);
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::OwnedMutexGuard;
+
+use crate::types::ExtensionIdentifier;
+
+// Serializes publishes of the same extension, publishes of different extensions still run concurrently.
+// A lock is only kept while it is held or waited for, so the map does not grow with every extension ever published.
+#[derive(Default)]
+pub struct PublishLocks {
+    locks: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+pub struct PublishGuard<'a> {
+    locks: &'a PublishLocks,
+    key: String,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl PublishLocks {
+    pub async fn lock(&self, id: &ExtensionIdentifier) -> PublishGuard<'_> {
+        let lock = self.locks.lock().unwrap()
+            .entry(id.as_key())
+            .or_default()
+            .clone();
+
+        PublishGuard {
+            locks: self,
+            key: id.as_key(),
+            guard: Some(lock.lock_owned().await),
+        }
+    }
+}
+
+impl Drop for PublishGuard<'_> {
+    fn drop(&mut self) {
+        self.guard.take();
+
+        // Anyone waiting for the lock holds a reference to it, the map's is the last one when nobody is.
+        let mut locks = self.locks.locks.lock().unwrap();
+        if locks.get(&self.key).is_some_and(|it| Arc::strong_count(it) == 1) {
+            locks.remove(&self.key);
+        }
+    }
+}
+
+// A directory under `static/.staging` a version is written to before it is moved into place, or a deleted version is
+// moved to before it is forgotten.
+// Rocket never serves paths with a segment starting with '.', so nothing staged can be downloaded.
+// Everything left in it, including the version it replaced, is removed when it is dropped.
+pub struct Staging {
+    root: PathBuf,
+}
+
+impl Staging {
+    pub fn new() -> io::Result<Staging> {
+        let root = Path::new("static/.staging").join(hex::encode(rand::random::<[u8; 16]>()));
+        fs::create_dir_all(root.join("files"))?;
+
+        Ok(Staging {
+            root,
+        })
+    }
+
+    // Staging directories left behind by a crash, removed on startup before anything is staged.
+    pub fn clean_up() -> io::Result<()> {
+        match fs::remove_dir_all("static/.staging") {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn files(&self) -> PathBuf {
+        self.root.join("files")
+    }
+
+    // Where a version that is being replaced is kept until the publish is committed.
+    pub fn backup(&self) -> PathBuf {
+        self.root.join("backup")
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+// Undoes the completed steps of a publish or delete in reverse order, dropping it without running it commits them.
+// Steps are best effort, a step that fails does not stop the ones after it.
+#[derive(Default)]
+pub struct Rollback<'a> {
+    steps: Vec<Box<dyn FnOnce() + Send + 'a>>,
+}
+
+impl<'a> Rollback<'a> {
+    pub fn push(&mut self, step: impl FnOnce() + Send + 'a) {
+        self.steps.push(Box::new(step));
+    }
+
+    pub fn run(self) {
+        for step in self.steps.into_iter().rev() {
+            step();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::publish::{PublishLocks, Rollback};
+    use crate::types::ExtensionIdentifier;
+
+    #[test]
+    fn test_rollback_runs_in_reverse() {
+        let steps = Arc::new(Mutex::new(Vec::new()));
+
+        let mut rollback = Rollback::default();
+        for step in 0..3 {
+            let steps = steps.clone();
+            rollback.push(move || steps.lock().unwrap().push(step));
+        }
+        rollback.run();
+
+        assert_eq!(*steps.lock().unwrap(), vec![2, 1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_publish_locks_are_pruned() {
+        let locks = PublishLocks::default();
+        let id = ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "locked".into(),
+        };
+
+        let guard = locks.lock(&id).await;
+        assert_eq!(locks.locks.lock().unwrap().len(), 1);
+        drop(guard);
+        assert!(locks.locks.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use rocket::http::Status;
+use serde::Serialize;
+
+use crate::metadata::MetadataHandler;
+use crate::responses::{HandlerError, HttpResult};
+use crate::snapshot::is_snapshot;
+use crate::types::{ExtensionDescriptor, ExtensionParent, ExtensionRuntimeModel};
+use crate::version::VersionRange;
+
+#[derive(Serialize)]
+pub struct ResolvedExtension {
+    pub descriptor: String,
+    pub erm_path: String,
+    pub yanked: bool,
+    // Descriptors of the direct parents with ranges resolved to versions, each of them is part of the same resolution.
+    pub parents: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResolutionError {
+    // A parent that has not been published.
+    Missing {
+        descriptor: String,
+        required_by: String,
+    },
+    // Descriptors leading from an extension back to itself, the first and last are the same.
+    Cycle {
+        path: Vec<String>,
+    },
+}
+
+#[derive(Serialize)]
+pub struct Resolution {
+    pub extension: String,
+    // Every extension of the closure exactly once, parents come before the extensions that need them.
+    pub extensions: Vec<ResolvedExtension>,
+    pub errors: Vec<ResolutionError>,
+}
+
+// Where the ERM of a version lives under the registry, a snapshot resolves to its newest build.
+pub fn erm_path(descriptor: &ExtensionDescriptor, metadata_handler: &MetadataHandler) -> Option<PathBuf> {
+    let identifier = descriptor.identifier();
+    let file_version = if is_snapshot(&descriptor.version) {
+        metadata_handler.latest_snapshot_build(&identifier, &descriptor.version)?
+    } else {
+        descriptor.version.clone()
+    };
+
+    Some(identifier.path()
+        .join(&descriptor.version)
+        .join(format!("{}-{}-erm.json", descriptor.extension, file_version)))
+}
+
+fn load_erm(root: &Path, path: &Path) -> HttpResult<Option<ExtensionRuntimeModel>> {
+    let path = root.join(path);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let erm = serde_json::from_reader(File::open(&path)?).map_err(|e| HandlerError::server_error(
+        "Internal server error".into(),
+        Some(format!("Published ERM '{}' is invalid: {}", path.display(), e)),
+    ))?;
+
+    Ok(Some(erm))
+}
+
+// The version a parent refers to, a range resolves to the highest published version in it that is not yanked.
+// A parent that is not a valid range is returned as declared, no published version can match it.
+pub fn resolve_parent(parent: &ExtensionParent, metadata_handler: &MetadataHandler) -> Option<ExtensionDescriptor> {
+    let descriptor = parent.to_descriptor();
+
+    match VersionRange::parse(&parent.version) {
+        // A plain version is already what it refers to, `[1.0]` still has to be looked up.
+        Some(range) if range.is_exact() && !parent.version.trim().starts_with('[') => Some(descriptor),
+        Some(range) => Some(ExtensionDescriptor {
+            version: metadata_handler.resolve_range(&descriptor.identifier(), &range)?,
+            ..descriptor
+        }),
+        None => Some(descriptor),
+    }
+}
+
+struct Resolver<'a> {
+    // The registry directory ERMs are read from, `static` when serving.
+    root: &'a Path,
+    metadata_handler: &'a MetadataHandler,
+    visited: HashSet<String>,
+    // Extensions currently being resolved, a parent found in here closes a cycle.
+    stack: Vec<String>,
+    extensions: Vec<ResolvedExtension>,
+    errors: Vec<ResolutionError>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(root: &'a Path, metadata_handler: &'a MetadataHandler) -> Resolver<'a> {
+        Resolver {
+            root,
+            metadata_handler,
+            visited: HashSet::new(),
+            stack: Vec::new(),
+            extensions: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    // Returns false when the extension has not been published.
+    fn visit(&mut self, descriptor: &ExtensionDescriptor) -> HttpResult<bool> {
+        let key = descriptor.as_key();
+        if let Some(start) = self.stack.iter().position(|it| *it == key) {
+            let mut path = self.stack[start..].to_vec();
+            path.push(key);
+            self.errors.push(ResolutionError::Cycle { path });
+
+            return Ok(true);
+        }
+        if self.visited.contains(&key) {
+            return Ok(true);
+        }
+
+        let Some(path) = erm_path(descriptor, self.metadata_handler) else {
+            return Ok(false);
+        };
+        let Some(erm) = load_erm(self.root, &path)? else {
+            return Ok(false);
+        };
+
+        self.stack.push(key.clone());
+        let mut parents = Vec::new();
+        for parent in &erm.parents {
+            let resolved = resolve_parent(parent, self.metadata_handler);
+            match &resolved {
+                Some(resolved) if self.visit(resolved)? => parents.push(resolved.as_key()),
+                _ => self.errors.push(ResolutionError::Missing {
+                    descriptor: parent.to_descriptor().as_key(),
+                    required_by: key.clone(),
+                }),
+            }
+        }
+        self.stack.pop();
+
+        self.visited.insert(key.clone());
+        self.extensions.push(ResolvedExtension {
+            descriptor: key,
+            erm_path: format!("/registry/{}", path.to_str().unwrap()),
+            yanked: self.metadata_handler.is_yanked(&descriptor.identifier(), &descriptor.version),
+            parents,
+        });
+
+        Ok(true)
+    }
+}
+
+// Walks the parents of a published extension through their published ERMs.
+// Missing parents and cycles do not stop the walk, they are collected alongside everything that did resolve.
+pub fn resolve(root: &Path, descriptor: &ExtensionDescriptor, metadata_handler: &MetadataHandler) -> HttpResult<Resolution> {
+    let mut resolver = Resolver::new(root, metadata_handler);
+
+    if !resolver.visit(descriptor)? {
+        return Err(HandlerError::new(
+            "Extension not found".into(),
+            Some(format!("{} has not been published.", descriptor.as_key())),
+            Status::NotFound,
+        ));
+    }
+
+    Ok(Resolution {
+        extension: descriptor.as_key(),
+        extensions: resolver.extensions,
+        errors: resolver.errors,
+    })
+}
+
+// Cycles that publishing the runtime model would close, each path starts and ends with the runtime model itself.
+// The published ERM of the same version is never read, so overwriting a version checks the new parents only.
+pub fn introduced_cycles(
+    root: &Path,
+    runtime_model: &ExtensionRuntimeModel,
+    metadata_handler: &MetadataHandler,
+) -> HttpResult<Vec<Vec<String>>> {
+    let key = format!("{}:{}:{}", runtime_model.group_id, runtime_model.name, runtime_model.version);
+
+    let mut resolver = Resolver::new(root, metadata_handler);
+    resolver.stack.push(key.clone());
+    for parent in runtime_model.parents.iter().filter_map(|it| resolve_parent(it, metadata_handler)) {
+        resolver.visit(&parent)?;
+    }
+
+    Ok(resolver.errors.into_iter().filter_map(|it| match it {
+        ResolutionError::Cycle { path } if path[0] == key => Some(path),
+        _ => None,
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::metadata::MetadataHandler;
+    use crate::resolve::{resolve, ResolutionError};
+    use crate::types::{ExtensionDescriptor, ExtensionIdentifier, ExtensionParent, ExtensionRuntimeModel};
+
+    fn write_erm(root: &Path, name: &str, parents: &[&str]) {
+        let parents: Vec<(&str, &str)> = parents.iter().map(|it| (*it, "1.0")).collect();
+
+        write_erm_version(root, name, "1.0", &parents);
+    }
+
+    fn write_erm_version(root: &Path, name: &str, version: &str, parents: &[(&str, &str)]) {
+        let erm = ExtensionRuntimeModel {
+            api_version: 0,
+            group_id: "com.example".into(),
+            name: name.into(),
+            version: version.into(),
+            repositories: vec![],
+            parents: parents.iter().map(|(name, version)| ExtensionParent {
+                group: "com.example".into(),
+                extension: name.to_string(),
+                version: version.to_string(),
+            }).collect(),
+            partitions: vec![],
+            attributes: Default::default(),
+        };
+
+        let path = root.join("com/example").join(name).join(version);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join(format!("{}-{}-erm.json", name, version)), serde_json::to_vec(&erm).unwrap()).unwrap();
+    }
+
+    fn descriptor(name: &str) -> ExtensionDescriptor {
+        ExtensionDescriptor::parse_descriptor(&format!("com.example:{}:1.0", name)).unwrap()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path();
+        let handler = MetadataHandler::new();
+
+        write_erm(root, "resolve-app", &["resolve-lib", "resolve-core"]);
+        write_erm(root, "resolve-lib", &["resolve-core", "resolve-missing"]);
+        write_erm(root, "resolve-core", &[]);
+
+        let resolution = resolve(root, &descriptor("resolve-app"), &handler).unwrap();
+        let extensions: Vec<&str> = resolution.extensions.iter().map(|it| it.descriptor.as_str()).collect();
+        assert_eq!(extensions, vec!["com.example:resolve-core:1.0", "com.example:resolve-lib:1.0", "com.example:resolve-app:1.0"]);
+        assert_eq!(resolution.extensions[0].erm_path, "/registry/com/example/resolve-core/1.0/resolve-core-1.0-erm.json");
+        assert_eq!(resolution.errors, vec![ResolutionError::Missing {
+            descriptor: "com.example:resolve-missing:1.0".into(),
+            required_by: "com.example:resolve-lib:1.0".into(),
+        }]);
+
+        write_erm(root, "resolve-cycle-a", &["resolve-cycle-b"]);
+        write_erm(root, "resolve-cycle-b", &["resolve-cycle-a"]);
+
+        let resolution = resolve(root, &descriptor("resolve-cycle-a"), &handler).unwrap();
+        assert_eq!(resolution.extensions.len(), 2);
+        assert_eq!(resolution.errors, vec![ResolutionError::Cycle {
+            path: vec!["com.example:resolve-cycle-a:1.0".into(), "com.example:resolve-cycle-b:1.0".into(), "com.example:resolve-cycle-a:1.0".into()],
+        }]);
+
+        assert!(resolve(root, &descriptor("resolve-unpublished"), &handler).is_err());
+    }
+
+    #[test]
+    fn test_resolve_ranges() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path();
+        let handler = MetadataHandler::new();
+        let core = ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "resolve-range-core".into(),
+        };
+        for version in ["1.0", "1.4", "1.5", "2.0"] {
+            handler.new_version(core.clone(), version.into(), Default::default()).unwrap();
+            write_erm_version(root, "resolve-range-core", version, &[]);
+        }
+        handler.yank(&core, "1.5").unwrap();
+
+        write_erm_version(root, "resolve-range-app", "1.0", &[("resolve-range-core", "^1.0"), ("resolve-range-core", "[1.5]"), ("resolve-range-core", "(2.0,)")]);
+
+        let resolution = resolve(root, &descriptor("resolve-range-app"), &handler).unwrap();
+        assert_eq!(resolution.extensions.last().unwrap().parents, vec!["com.example:resolve-range-core:1.4", "com.example:resolve-range-core:1.5"]);
+        assert_eq!(resolution.errors, vec![ResolutionError::Missing {
+            descriptor: "com.example:resolve-range-core:(2.0,)".into(),
+            required_by: "com.example:resolve-range-app:1.0".into(),
+        }]);
+    }
+}
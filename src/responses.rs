@@ -1,18 +1,21 @@
-use rocket::futures::future::err;
-use rocket::http::Status;
-use rocket::Responder;
-use rocket::response::status;
+use std::io::Cursor;
+
+use rocket::http::{ContentType, Status};
+use rocket::{response, Request, Response};
+use rocket::response::Responder;
 use rocket::serde::json::Json;
 use serde::Serialize;
 
+use crate::signature::RepositorySigner;
+
 pub type HttpResult<T> = Result<T, HandlerError>;
 
-#[derive(Responder)]
+#[derive(Responder, Debug)]
 pub struct HandlerError {
     inner: (Status, Json<ErrorContent>),
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Debug)]
 struct ErrorContent {
     error_message: String,
     details: Option<String>,
@@ -38,4 +41,20 @@ impl HandlerError {
     ) -> HandlerError {
          Self::new(error_message, details, Status::InternalServerError)
     }
-}
\ No newline at end of file
+}
+
+// JSON response whose exact body is signed with the repository key, the signature goes in the X-Signature header.
+pub struct SignedJson<T>(pub T);
+
+impl<'r, T: Serialize> Responder<'r, 'static> for SignedJson<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let body = serde_json::to_vec(&self.0).map_err(|_| Status::InternalServerError)?;
+        let signer = request.rocket().state::<RepositorySigner>().expect("No repository signer provided!");
+
+        Response::build()
+            .header(ContentType::JSON)
+            .raw_header("X-Signature", signer.sign(&body))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
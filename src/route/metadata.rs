@@ -1,24 +1,26 @@
-use std::fmt::format;
-use std::fs::metadata;
-use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use rocket::{get, Route, routes, State};
+use rocket::{get, post, Route, routes, State};
 use rocket::http::Status;
-use rocket::route::Handler;
+use rocket::http::uri::{fmt, Segments};
+use rocket::request::FromSegments;
 use rocket::serde::json::Json;
 
 use crate::metadata::MetadataHandler;
-use crate::responses::{HandlerError, HttpResult};
-use crate::types::{ExtensionIdentifier, ManagedExtensionMetadata, RepositoryMetadata, VersionInfo, VersionType};
+use crate::responses::{HandlerError, HttpResult, SignedJson};
+use crate::signature::RepositorySigner;
+use crate::snapshot::is_snapshot;
+use crate::types::{DependentsResponse, ExtensionIdentifier, ManagedExtensionMetadata, RepositoryMetadata, SnapshotInfo, UpdateCheckRequest, UpdateCheckResponse, VersionInfo, VersionType};
 
 pub struct ExtensionMetadataServer;
 
-impl Into<Vec<Route>> for ExtensionMetadataServer {
-    fn into(self) -> Vec<Route> {
+impl From<ExtensionMetadataServer> for Vec<Route> {
+    fn from(_: ExtensionMetadataServer) -> Self {
         routes![
             get_managed_metadata,
-            get_metadata
+            get_dependents,
+            get_metadata,
+            check_updates
         ]
     }
 }
@@ -26,7 +28,8 @@ impl Into<Vec<Route>> for ExtensionMetadataServer {
 #[get("/metadata")]
 fn get_metadata(
     metadata: &State<RepositoryMetadata>,
-    metadata_handler: &State<MetadataHandler>
+    metadata_handler: &State<MetadataHandler>,
+    signer: &State<RepositorySigner>,
 ) -> Json<RepositoryMetadata> {
     Json(RepositoryMetadata {
         name: metadata.name.clone(),
@@ -34,14 +37,12 @@ fn get_metadata(
         icon: metadata.icon.clone(),
         extension_count: metadata_handler.extension_count(),
         app_ids: metadata.app_ids.clone(),
+        public_key: Some(signer.public_key()),
     })
 }
 
-#[get("/metadata/<path..>")]
-fn get_managed_metadata(
-    path: PathBuf,
-    metadata_handler: &State<MetadataHandler>,
-) -> HttpResult<Json<ManagedExtensionMetadata>> {
+// `com/example/name` is the extension `com.example:name`.
+fn identifier_from_path(path: &Path) -> HttpResult<ExtensionIdentifier> {
     let group_dots =
         path.parent().ok_or(HandlerError::new(
         "Invalid extension path".into(), None, Status::BadRequest,
@@ -49,24 +50,68 @@ fn get_managed_metadata(
         .map(|str| format!("{}.", str))
         .collect::<String>();
 
-    let group_dots = if let Some(x) = group_dots.strip_suffix(".") {
-        x
-    } else {
-        ""
-    }.to_string();
+    let group_dots = group_dots.strip_suffix(".").unwrap_or_default().to_string();
 
     let name = path.file_name().ok_or(HandlerError::new(
         "Invalid extension path".into(), None, Status::BadRequest,
     ))?.to_str().unwrap();
 
-    let identifier = ExtensionIdentifier {
+    Ok(ExtensionIdentifier {
         group: group_dots,
         name: name.to_string(),
-    };
+    })
+}
+
+// The extension path of `/metadata/<path..>/dependents`, any other path forwards to the managed metadata.
+pub struct DependentsPath(PathBuf);
+
+impl<'r> FromSegments<'r> for DependentsPath {
+    type Error = ();
+
+    fn from_segments(segments: Segments<'r, fmt::Path>) -> Result<Self, Self::Error> {
+        let path = PathBuf::from_segments(segments).map_err(|_| ())?;
+
+        match path.parent() {
+            Some(parent) if path.ends_with("dependents") && parent.components().count() > 1 => Ok(DependentsPath(parent.to_path_buf())),
+            _ => Err(()),
+        }
+    }
+}
+
+// Page index starts at 0
+#[get("/metadata/<path..>?<page>&<pagination>", rank = 1)]
+fn get_dependents(
+    path: DependentsPath,
+    page: Option<usize>,
+    pagination: Option<usize>,
+    metadata_handler: &State<MetadataHandler>,
+) -> HttpResult<Json<DependentsResponse>> {
+    let identifier = identifier_from_path(&path.0)?;
+    let page = page.unwrap_or(0);
+    let pagination = pagination.unwrap_or(20);
+
+    let dependents = metadata_handler.dependents(&identifier);
+
+    Ok(Json(DependentsResponse {
+        total: dependents.len(),
+        dependents: dependents.into_iter()
+            .skip(page.saturating_mul(pagination))
+            .take(pagination)
+            .collect(),
+    }))
+}
+
+#[get("/metadata/<path..>", rank = 2)]
+fn get_managed_metadata(
+    path: PathBuf,
+    metadata_handler: &State<MetadataHandler>,
+) -> HttpResult<SignedJson<ManagedExtensionMetadata>> {
+    let identifier = identifier_from_path(&path)?;
+    let name = identifier.name.clone();
 
     let (downloads, latest, versions) = metadata_handler.get_managed_metadata(&identifier)?;
 
-    Ok(Json(ManagedExtensionMetadata {
+    Ok(SignedJson(ManagedExtensionMetadata {
         downloads,
         latest,
         versions: versions.iter().map(|it| {
@@ -79,33 +124,62 @@ fn get_managed_metadata(
                     name,
                     it
                 ),
+                checksums: metadata_handler.checksums(&identifier, it),
+                yanked: metadata_handler.is_yanked(&identifier, it),
             })
         }).collect::<Result<Vec<_>, HandlerError>>()?,
+        snapshots: versions.iter()
+            .filter(|it| is_snapshot(it))
+            .map(|it| (it.clone(), metadata_handler.snapshot_builds(&identifier, it).into_iter().map(|build| SnapshotInfo {
+                metadata_path: format!(
+                    "/registry/{}/{}/{}-{}-metadata.json",
+                    path.to_str().unwrap(),
+                    it,
+                    name,
+                    build.version
+                ),
+                version: build.version,
+                timestamp: build.timestamp,
+                build_number: build.build_number,
+                checksums: build.checksums,
+            }).collect()))
+            .collect(),
+    }))
+}
+
+#[post("/updates", format = "json", data = "<request>")]
+fn check_updates(
+    request: Json<UpdateCheckRequest>,
+    metadata_handler: &State<MetadataHandler>,
+) -> HttpResult<SignedJson<UpdateCheckResponse>> {
+    let channel = VersionType::from_channel(&request.channel).ok_or_else(|| HandlerError::new(
+        "Invalid channel".into(),
+        Some(format!("'{}' is not a channel, must be one of release, alpha, beta, rc or snapshot.", request.channel)),
+        Status::BadRequest,
+    ))?;
+
+    Ok(SignedJson(UpdateCheckResponse {
+        updates: metadata_handler.check_updates(&request.extensions, &channel),
     }))
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
+    use std::collections::HashMap;
+
+    use rocket::http::Status;
     use rocket::uri;
 
     use crate::metadata::MetadataHandler;
     use crate::route::metadata::ExtensionMetadataServer;
-    use crate::types::RepositoryMetadata;
+    use crate::route::testing::test_rocket;
+    use crate::signature::{parse_key, parse_signature, verify, RepositorySigner};
+    use crate::types::{Checksums, ExtensionIdentifier, ExtensionParent, UpdateCheckResponse};
 
     #[tokio::test]
     async fn test_get_repository_metadata() {
         let client = rocket::local::asynchronous::Client::tracked(
-            rocket::build()
-                .mount("/", ExtensionMetadataServer)
-                .manage(MetadataHandler::hydrate_cache("config/metadata.json").unwrap())
-                .manage(RepositoryMetadata {
-                    name: "A test repository".to_string(),
-                    description: "A cool description".to_string(),
-                    icon: Default::default(),
-                    extension_count: 0,
-                    app_ids: vec![],
-                })
+            test_rocket().mount("/", ExtensionMetadataServer)
         ).await.unwrap();
 
         let r = client.get(uri!(super::get_metadata))
@@ -114,30 +188,114 @@ mod tests {
         println!("{}", r.into_string().await.unwrap_or("no body".to_string()));
 
         let handler : &MetadataHandler = client.rocket().state().unwrap();
-        handler.persist_to("config/metadata.json").unwrap();
+        handler.checkpoint().unwrap();
     }
 
     #[tokio::test]
     async fn test_get_managed_metadata() {
         let client = rocket::local::asynchronous::Client::tracked(
-            rocket::build()
-                .mount("/", ExtensionMetadataServer)
-                .manage(MetadataHandler::hydrate_cache("config/metadata.json").unwrap())
-                .manage(RepositoryMetadata {
-                    name: "A test repository".to_string(),
-                    description: "A cool description".to_string(),
-                    icon: Default::default(),
-                    extension_count: 0,
-                    app_ids: vec![],
-                })
+            test_rocket().mount("/", ExtensionMetadataServer)
         ).await.unwrap();
 
         let r = client.get("/metadata/com/example/testing")
             .dispatch().await;
 
-        println!("{}", r.into_string().await.unwrap_or("no body".to_string()));
+        let signer: &RepositorySigner = client.rocket().state().unwrap();
+        let signature = parse_signature(r.headers().get_one("X-Signature").unwrap()).unwrap();
+        let body = r.into_bytes().await.unwrap();
+        assert!(verify(&[parse_key(&signer.public_key()).unwrap()], &body, &signature));
+
+        println!("{}", String::from_utf8(body).unwrap());
 
         let handler : &MetadataHandler = client.rocket().state().unwrap();
-        handler.persist_to("config/metadata.json").unwrap();
+        handler.checkpoint().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_dependents() {
+        let client = rocket::local::asynchronous::Client::tracked(
+            test_rocket().mount("/", ExtensionMetadataServer)
+        ).await.unwrap();
+
+        let handler: &MetadataHandler = client.rocket().state().unwrap();
+        let parents = vec![ExtensionParent {
+            group: "com.example".into(),
+            extension: "core".into(),
+            version: "1.0".into(),
+        }];
+        for name in ["b", "a", "c"] {
+            let id = ExtensionIdentifier {
+                group: "com.example".into(),
+                name: name.into(),
+            };
+            handler.index_parents(&id, "1.0", &parents).unwrap();
+        }
+        // Republishing replaces what a version was indexed with.
+        handler.index_parents(&ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "c".into(),
+        }, "1.0", &[]).unwrap();
+
+        let r = client.get("/metadata/com/example/core/dependents?page=1&pagination=1")
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+
+        let body: serde_json::Value = r.into_json().await.unwrap();
+        assert_eq!(body["total"], 2);
+        assert_eq!(body["dependents"], serde_json::json!([{ "descriptor": "com.example:b:1.0", "parent_version": "1.0" }]));
+
+        // Anything else under /metadata is still the managed metadata of an extension.
+        let r = client.get("/metadata/com/example/core")
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+        assert!(r.headers().get_one("X-Signature").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_check_updates() {
+        let client = rocket::local::asynchronous::Client::tracked(
+            test_rocket().mount("/", ExtensionMetadataServer)
+        ).await.unwrap();
+
+        let handler: &MetadataHandler = client.rocket().state().unwrap();
+        let id = ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "updates".into(),
+        };
+        for version in ["1.0", "1.1", "1.2", "2.0-beta"] {
+            let checksums = HashMap::from([(format!("updates-{}-erm.json", version), Checksums {
+                sha256: "".into(),
+                sha1: "".into(),
+                md5: "".into(),
+            })]);
+            handler.new_version(id.clone(), version.into(), checksums).unwrap();
+        }
+        handler.yank(&id, "1.0").unwrap();
+        handler.yank(&id, "1.2").unwrap();
+
+        let check = |channel: &str| client.post(uri!(super::check_updates))
+            .json(&serde_json::json!({
+                "channel": channel,
+                "extensions": [
+                    { "group": "com.example", "extension": "updates", "version": "1.0" },
+                    { "group": "com.example", "extension": "unknown", "version": "1.0" }
+                ]
+            }))
+            .dispatch();
+
+        let r = check("release").await;
+        assert_eq!(r.status(), Status::Ok);
+        let response: UpdateCheckResponse = r.into_json().await.unwrap();
+        assert_eq!(response.updates[0].latest, Some("1.1".to_string()));
+        assert!(response.updates[0].update_available);
+        assert!(response.updates[0].yanked);
+        assert_eq!(response.updates[0].download_paths, vec!["/registry/com/example/updates/1.1/updates-1.1-erm.json"]);
+        assert_eq!(response.updates[1].latest, None);
+        assert!(!response.updates[1].update_available);
+
+        let response: UpdateCheckResponse = check("beta").await.into_json().await.unwrap();
+        assert_eq!(response.updates[0].latest, Some("2.0-beta".to_string()));
+
+        assert_eq!(check("nightly").await.status(), Status::BadRequest);
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,9 @@
 pub mod registry;
 pub mod metadata;
+pub mod owners;
+pub mod resolve;
 pub mod search;
+pub mod tokens;
 
-
+#[cfg(test)]
+pub mod testing;
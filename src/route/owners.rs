@@ -0,0 +1,152 @@
+use rocket::{delete, get, post, put, Route, routes, State};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+
+use crate::auth::{Authorization, Scope};
+use crate::ownership::OwnershipStore;
+use crate::responses::{HandlerError, HttpResult};
+use crate::types::{GroupOwners, OwnerRequest};
+
+pub struct GroupOwnerServer;
+
+impl From<GroupOwnerServer> for Vec<Route> {
+    fn from(_: GroupOwnerServer) -> Self {
+        routes![
+            get_owners,
+            share_group,
+            transfer_group,
+            remove_owner
+        ]
+    }
+}
+
+fn unowned(group: &str) -> HandlerError {
+    HandlerError::new(
+        "Group not owned".into(),
+        Some(format!("Nothing has been published into '{}' yet.", group)),
+        Status::NotFound,
+    )
+}
+
+// Ownership is managed by admins of the group, who may publish into it regardless of who owns it.
+#[get("/groups/<group>/owners")]
+fn get_owners(
+    group: &str,
+    authorized: Authorization,
+    ownership: &State<OwnershipStore>,
+) -> HttpResult<Json<GroupOwners>> {
+    authorized.require(Scope::Admin, group)?;
+
+    let owners = ownership.owners(group).ok_or_else(|| unowned(group))?;
+
+    Ok(Json(GroupOwners {
+        group: group.to_string(),
+        owners,
+    }))
+}
+
+#[post("/groups/<group>/owners", format = "json", data = "<request>")]
+fn share_group(
+    group: &str,
+    request: Json<OwnerRequest>,
+    authorized: Authorization,
+    ownership: &State<OwnershipStore>,
+) -> HttpResult<Json<GroupOwners>> {
+    authorized.require(Scope::Admin, group)?;
+
+    Ok(Json(GroupOwners {
+        group: group.to_string(),
+        owners: ownership.share(group, &request.principal)?,
+    }))
+}
+
+#[put("/groups/<group>/owners", format = "json", data = "<request>")]
+fn transfer_group(
+    group: &str,
+    request: Json<OwnerRequest>,
+    authorized: Authorization,
+    ownership: &State<OwnershipStore>,
+) -> HttpResult<Json<GroupOwners>> {
+    authorized.require(Scope::Admin, group)?;
+
+    Ok(Json(GroupOwners {
+        group: group.to_string(),
+        owners: ownership.transfer(group, &request.principal)?,
+    }))
+}
+
+#[delete("/groups/<group>/owners/<principal>")]
+fn remove_owner(
+    group: &str,
+    principal: &str,
+    authorized: Authorization,
+    ownership: &State<OwnershipStore>,
+) -> HttpResult<()> {
+    authorized.require(Scope::Admin, group)?;
+
+    if !ownership.remove(group, principal)? {
+        return Err(HandlerError::new(
+            "Not an owner".into(),
+            Some(format!("'{}' does not own '{}'.", principal, group)),
+            Status::NotFound,
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::asynchronous::Client;
+
+    use crate::ownership::OwnershipStore;
+    use crate::route::owners::GroupOwnerServer;
+    use crate::route::testing::test_rocket;
+    use crate::types::GroupOwners;
+
+    #[tokio::test]
+    async fn test_transfer_and_share() {
+        let client = Client::tracked(test_rocket().mount("/", GroupOwnerServer)).await.unwrap();
+        let ownership: &OwnershipStore = client.rocket().state().unwrap();
+        ownership.claim("com.example", "team-a").unwrap();
+
+        let r = client.put("/groups/com.example/owners")
+            .header(Header::new("Authorization", "Bearer team-a"))
+            .header(ContentType::JSON)
+            .body(r#"{ "principal": "team-b" }"#)
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Forbidden);
+
+        let r = client.post("/groups/com.example/owners")
+            .header(Header::new("Authorization", "Bearer admin"))
+            .header(ContentType::JSON)
+            .body(r#"{ "principal": "team-b" }"#)
+            .dispatch().await;
+        let owners: GroupOwners = r.into_json().await.unwrap();
+        assert_eq!(owners.owners, vec!["team-a".to_string(), "team-b".to_string()]);
+
+        let r = client.put("/groups/com.example/owners")
+            .header(Header::new("Authorization", "Bearer admin"))
+            .header(ContentType::JSON)
+            .body(r#"{ "principal": "team-c" }"#)
+            .dispatch().await;
+        let owners: GroupOwners = r.into_json().await.unwrap();
+        assert_eq!(owners.owners, vec!["team-c".to_string()]);
+
+        let r = client.delete("/groups/com.example/owners/team-a")
+            .header(Header::new("Authorization", "Bearer admin"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::NotFound);
+
+        let r = client.delete("/groups/com.example/owners/team-c")
+            .header(Header::new("Authorization", "Bearer admin"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+
+        let r = client.get("/groups/com.example/owners")
+            .header(Header::new("Authorization", "Bearer admin"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::NotFound);
+    }
+}
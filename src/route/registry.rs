@@ -1,37 +1,43 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
 use std::fs::File;
 use std::io;
-use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::io::{Read, Seek, Write};
 use std::path::{Path, PathBuf};
 
-use rocket::{get, put, Route, routes, State};
-use rocket::figment::Source::{Custom};
-use rocket::fs::{FileServer, NamedFile, TempFile};
-use rocket::futures::{AsyncReadExt, TryFutureExt};
-use rocket::http::hyper::Version;
+use rocket::{delete, get, post, put, Route, routes, State};
+use rocket::fs::{NamedFile, TempFile};
 use rocket::http::Status;
-use rocket::response::status;
-use rocket::route::{Cloneable, Handler, Outcome};
-use serde::de::Unexpected::Str;
-use tempfile::{NamedTempFile, tempfile, tempfile_in};
-use tokio::fs::create_dir_all;
-use zip::read::ZipFile;
-use zip::result::{ZipError, ZipResult};
+use rocket::serde::json::Json;
+use tempfile::NamedTempFile;
+use zip::result::ZipError;
 use zip::ZipArchive;
 
-use crate::auth::{Authorization, Authorizer};
+use crate::auth::{Authorization, Scope};
+use crate::checksum::{is_sidecar, parse_sidecar, sidecar_target, ChecksumWriter, SIDECAR_EXTENSIONS};
+use crate::config::{BundleLimits, ParentPolicy, PublisherKeys, ServerConfig};
 use crate::metadata::MetadataHandler;
+use crate::ownership::OwnershipStore;
+use crate::publish::{PublishLocks, Rollback, Staging};
+use crate::resolve::{introduced_cycles, resolve_parent};
 use crate::responses::{HandlerError, HttpResult};
 use crate::search::ExtensionSearchHandler;
-use crate::types::{ExtensionBundle, ExtensionIdentifier, ExtensionMetadata, ExtensionRuntimeModel, PartitionRuntimeModel, VersionType};
+use crate::signature::{manifest, parse_key, parse_signature, verify, RepositorySigner, MANIFEST_ENTRY, REPOSITORY_SIGNATURE_EXTENSION, SIGNATURE_ENTRY};
+use crate::snapshot::{build_version, is_snapshot, remove_build_files, timestamp};
+use crate::types::{check_coordinates, Checksums, ExtensionBundle, ExtensionDescriptor, ExtensionIdentifier, ExtensionMetadata, ExtensionRuntimeModel, PartitionRuntimeModel, PublishResponse, SnapshotBuild, VersionType};
+use crate::version::{Version, VersionRange};
 
 #[derive(Debug, Clone)]
 pub struct ExtensionFileServer;
 
-impl Into<Vec<Route>> for ExtensionFileServer {
-    fn into(self) -> Vec<Route> {
+impl From<ExtensionFileServer> for Vec<Route> {
+    fn from(_: ExtensionFileServer) -> Self {
         routes![
             get_object,
-            put_object
+            put_object,
+            yank_version,
+            unyank_version,
+            delete_version
         ]
     }
 }
@@ -41,7 +47,14 @@ async fn get_object(
     path: PathBuf,
     metadata_handler: &State<MetadataHandler>,
 ) -> HttpResult<NamedFile> {
-    let path = Path::new("static").join(path);
+    let mut file_path = Path::new("static").join(&path);
+    if !file_path.exists() {
+        if let Some(resolved) = resolve_snapshot(&path, metadata_handler) {
+            file_path = resolved;
+        }
+    }
+
+    let path = file_path;
     if !path.exists() {
         return Err(
             HandlerError::new(
@@ -63,55 +76,378 @@ async fn get_object(
     Ok(NamedFile::open(path).await?)
 }
 
-#[put("/registry", data = "<data>")]
+// A file requested under a snapshot version, like `name-1.3.0-SNAPSHOT-erm.json`, is served from its newest build.
+fn resolve_snapshot(path: &Path, metadata_handler: &MetadataHandler) -> Option<PathBuf> {
+    let components = path.iter()
+        .map(|it| it.to_str())
+        .collect::<Option<Vec<_>>>()?;
+    let [group @ .., name, version, file_name] = components.as_slice() else {
+        return None;
+    };
+    if group.is_empty() || !is_snapshot(version) {
+        return None;
+    }
+
+    let rest = file_name.strip_prefix(&format!("{}-{}", name, version))?;
+    let identifier = ExtensionIdentifier {
+        group: group.join("."),
+        name: name.to_string(),
+    };
+    let build = metadata_handler.latest_snapshot_build(&identifier, version)?;
+
+    Some(Path::new("static").join(path.parent()?).join(format!("{}-{}{}", name, build, rest)))
+}
+
+// Published versions are immutable, an admin may replace one by passing `?overwrite=true`.
+// Snapshot versions are the exception, every upload of one is kept as a new timestamped build.
+#[put("/registry?<overwrite>", data = "<data>")]
+#[allow(clippy::too_many_arguments)]
 async fn put_object(
     mut data: TempFile<'_>,
+    overwrite: Option<bool>,
     authorized: Authorization,
     metadata_handler: &State<MetadataHandler>,
     search_handler: &ExtensionSearchHandler,
-) -> HttpResult<()> {
+    config: &State<ServerConfig>,
+    signer: &State<RepositorySigner>,
+    publish_locks: &State<PublishLocks>,
+    ownership: &State<OwnershipStore>,
+) -> HttpResult<Json<PublishResponse>> {
+    let limits = config.bundle_limits.clone();
+    let signer = signer.inner().clone();
+
     let file = NamedTempFile::new()?
         .into_temp_path();
 
     data.persist_to(&file).await?;
 
-    let mut bundle = build_bundle_from(File::open(file)?).await?;
-    validate_bundle(&bundle)?;
+    let read = File::open(&file)?;
+    let read_limits = limits.clone();
+    let mut bundle = blocking(move || {
+        let mut bundle = build_bundle_from(read, &read_limits)?;
+        validate_bundle(&mut bundle)?;
 
-    let path = bundle.runtime_model.group_id.split(".").fold(PathBuf::from("static/"), |acc, it| {
-        acc.join(it)
-    }).join(bundle.runtime_model.name.clone()).join(bundle.runtime_model.version.clone());
+        Ok(bundle)
+    }).await?;
 
-    write_bundle(path, &mut bundle).await?;
+    let group = bundle.runtime_model.group_id.clone();
+    authorized.require(Scope::Publish, &group)?;
+    // Admins publish into any group without claiming it.
+    let claims = !authorized.principal.allows(Scope::Admin, &group);
+    if claims {
+        ownership.check(&group, &authorized.principal.id)?;
+    }
 
-    metadata_handler.new_version(
-        (&bundle.runtime_model).into(),
-        bundle.runtime_model.version.clone(),
-    )?;
+    let publisher = config.publishers.get(&bundle.runtime_model.group_id).cloned();
+    if publisher.as_ref().is_some_and(|it| it.require_signature) && !bundle.files.iter().any(|it| it == SIGNATURE_ENTRY) {
+        return Err(HandlerError::new(
+            "Unsigned extension bundle".into(),
+            Some(format!(
+                "Bundles published to '{}' must be signed with a registered publisher key in '{}'.",
+                bundle.runtime_model.group_id, SIGNATURE_ENTRY
+            )),
+            Status::BadRequest,
+        ));
+    }
 
-    let metadata = bundle.metadata;
+    let identifier: ExtensionIdentifier = (&bundle.runtime_model).into();
+    // Held until the publish is committed or rolled back, so a concurrent publish of the extension sees its outcome.
+    let _lock = publish_locks.lock(&identifier).await;
 
-    let identifier = ExtensionIdentifier {
-        group: bundle.runtime_model.group_id.clone(),
-        name: bundle.runtime_model.name.clone(),
-    };
+    let warnings = check_parents(&bundle.runtime_model, metadata_handler)?;
+    if !warnings.is_empty() && config.parent_policy == ParentPolicy::Reject {
+        return Err(HandlerError::new(
+            "Invalid extension parents".into(),
+            Some(warnings.join("\n")),
+            Status::BadRequest,
+        ));
+    }
 
-    let mut handler = search_handler.lock().unwrap();
-    // Names will arbitrarily index with higher ranks so that search by name comes up first
-    handler.index(
-        metadata.name.as_str(),
-        identifier.clone(),
-        10,
-    )?;
-
-    // Description will arbitrarily index with lower ranks.
-    handler.index(
-        metadata.description.as_str(),
-        identifier.clone(),
-        1,
-    )?;
+    let version = bundle.runtime_model.version.clone();
+    let path = Path::new("static").join(identifier.path()).join(&version);
 
-    Ok(())
+    let build = is_snapshot(&version)
+        .then(|| (timestamp(), metadata_handler.next_build_number(&identifier, &version)));
+    if let Some((timestamp, build_number)) = &build {
+        bundle.file_version = build_version(&version, timestamp, *build_number);
+    }
+
+    // Both would be served as the same version, overwriting one with the other would keep both.
+    if let Some(published) = metadata_handler.equivalent_version(&identifier, &version) {
+        return Err(HandlerError::new(
+            "Version already published".into(),
+            Some(format!(
+                "{}:{} is already published as {}.",
+                identifier.as_key(),
+                version,
+                published
+            )),
+            Status::Conflict,
+        ));
+    }
+
+    if build.is_none() && (metadata_handler.has_version(&identifier, &version) || path.exists()) {
+        if !overwrite.unwrap_or(false) {
+            return Err(HandlerError::new(
+                "Version already published".into(),
+                Some(format!(
+                    "{}:{} is already published and versions are immutable.",
+                    identifier.as_key(),
+                    bundle.runtime_model.version
+                )),
+                Status::Conflict,
+            ));
+        }
+
+        if !authorized.principal.allows(Scope::Admin, &identifier.group) {
+            return Err(HandlerError::new(
+                "Forbidden".into(),
+                Some("Overwriting a published version requires admin authorization.".into()),
+                Status::Forbidden,
+            ));
+        }
+    }
+
+    // Nothing outside of the staging directory is touched until every file is written and verified.
+    let staging = Staging::new()?;
+    let write_path = staging.files();
+    let (bundle, checksums) = blocking(move || {
+        let mut checksums = write_bundle(&write_path, &mut bundle, &limits, &signer)?;
+        verify_signature(&write_path, &mut bundle, &mut checksums, publisher.as_ref(), &limits, &signer)?;
+
+        Ok((bundle, checksums))
+    }).await?;
+
+    let metadata_handler = metadata_handler.inner().clone();
+    let search_handler = search_handler.inner().clone();
+    let ownership = ownership.inner().clone();
+    let principal = authorized.principal.id.clone();
+    let retention = config.snapshots.retention;
+
+    // Moving the files into place and saving the owners, metadata and index all wait on the disk.
+    blocking(move || {
+        let name = bundle.runtime_model.name.clone();
+        let file_version = bundle.file_version.clone();
+
+        let mut rollback = Rollback::default();
+        let committed = (|| -> HttpResult<Vec<SnapshotBuild>> {
+            if claims && ownership.claim(&group, &principal)? {
+                rollback.push(|| {
+                    let _ = ownership.release(&group);
+                });
+            }
+
+            if build.is_some() {
+                // Builds share the version directory, their files are moved in one by one.
+                rollback.push(|| {
+                    let _ = remove_build_files(&path, &name, &file_version);
+                });
+                fs::create_dir_all(&path)?;
+                for entry in fs::read_dir(staging.files())? {
+                    let entry = entry?;
+                    fs::rename(entry.path(), path.join(entry.file_name()))?;
+                }
+            } else {
+                // The version being replaced is kept aside so it can be put back.
+                fs::create_dir_all(path.parent().unwrap())?;
+                if path.exists() {
+                    let backup = staging.backup();
+                    fs::rename(&path, &backup)?;
+                    rollback.push(|| {
+                        let _ = fs::rename(backup, &path);
+                    });
+                }
+
+                fs::rename(staging.files(), &path)?;
+                rollback.push(|| {
+                    let _ = fs::remove_dir_all(&path);
+                });
+            }
+
+            // Tokens are added to what the extension was indexed with before, which is put back as a whole.
+            let indexed = search_handler.lock().unwrap().entries(&identifier);
+            search_handler.lock().unwrap().index_all(
+                &[
+                    // Names will arbitrarily index with higher ranks so that search by name comes up first
+                    (bundle.metadata.name.as_str(), 10),
+                    // Description will arbitrarily index with lower ranks.
+                    (bundle.metadata.description.as_str(), 1),
+                ],
+                identifier.clone(),
+            )?;
+            rollback.push(|| {
+                let mut search_handler = search_handler.lock().unwrap();
+                search_handler.restore(&identifier, indexed);
+                let _ = search_handler.persist();
+            });
+
+            let previous = metadata_handler.version_state(&identifier, &version);
+            rollback.push(|| {
+                let _ = metadata_handler.restore(previous);
+            });
+
+            let expired = match build {
+                Some((timestamp, build_number)) => metadata_handler.new_snapshot_build(
+                    identifier.clone(),
+                    version.clone(),
+                    SnapshotBuild {
+                        version: file_version.clone(),
+                        timestamp,
+                        build_number,
+                        checksums,
+                    },
+                    retention,
+                )?,
+                None => {
+                    metadata_handler.new_version(identifier.clone(), version.clone(), checksums)?;
+                    Vec::new()
+                }
+            };
+            metadata_handler.index_parents(&identifier, &version, &bundle.runtime_model.parents)?;
+
+            search_handler.lock().unwrap().persist()?;
+
+            Ok(expired)
+        })();
+
+        let expired = match committed {
+            Ok(expired) => expired,
+            Err(e) => {
+                rollback.run();
+                return Err(e);
+            }
+        };
+
+        // Expired builds are forgotten by the metadata already, their files are only cleaned up.
+        for build in expired {
+            let _ = remove_build_files(&path, &name, &build.version);
+        }
+
+        Ok(())
+    }).await?;
+
+    Ok(Json(PublishResponse {
+        warnings,
+    }))
+}
+
+#[post("/registry/<descriptor>/yank")]
+fn yank_version(
+    descriptor: &str,
+    authorized: Authorization,
+    metadata_handler: &State<MetadataHandler>,
+) -> HttpResult<()> {
+    let descriptor = ExtensionDescriptor::parse_descriptor(descriptor)?;
+    authorized.require(Scope::Yank, &descriptor.group)?;
+
+    metadata_handler.yank(&descriptor.identifier(), &descriptor.version)
+}
+
+#[delete("/registry/<descriptor>/yank")]
+fn unyank_version(
+    descriptor: &str,
+    authorized: Authorization,
+    metadata_handler: &State<MetadataHandler>,
+) -> HttpResult<()> {
+    let descriptor = ExtensionDescriptor::parse_descriptor(descriptor)?;
+    authorized.require(Scope::Yank, &descriptor.group)?;
+
+    metadata_handler.unyank(&descriptor.identifier(), &descriptor.version)
+}
+
+// Hard deletes a version, its files are removed and it is forgotten as if it was never published.
+#[delete("/registry/<descriptor>")]
+async fn delete_version(
+    descriptor: &str,
+    authorized: Authorization,
+    metadata_handler: &State<MetadataHandler>,
+    search_handler: &ExtensionSearchHandler,
+    publish_locks: &State<PublishLocks>,
+) -> HttpResult<()> {
+    let descriptor = ExtensionDescriptor::parse_descriptor(descriptor)?;
+    authorized.require(Scope::Delete, &descriptor.group)?;
+    let identifier = descriptor.identifier();
+    let _lock = publish_locks.lock(&identifier).await;
+
+    let metadata_handler = metadata_handler.inner().clone();
+    let search_handler = search_handler.inner().clone();
+
+    // Moving the files aside and saving the metadata and index all wait on the disk.
+    blocking(move || {
+        let path = Path::new("static").join(identifier.path()).join(&descriptor.version);
+        // The files are moved aside before the metadata forgets the version, and put back if it cannot.
+        let staging = Staging::new()?;
+
+        let mut rollback = Rollback::default();
+        let committed = (|| -> HttpResult<()> {
+            if path.exists() {
+                fs::rename(&path, staging.backup())?;
+                rollback.push(|| {
+                    let _ = fs::rename(staging.backup(), &path);
+                });
+            }
+
+            let previous = metadata_handler.version_state(&identifier, &descriptor.version);
+            let last_version = metadata_handler.delete_version(&identifier, &descriptor.version)?;
+            rollback.push(|| {
+                let _ = metadata_handler.restore(previous);
+            });
+
+            if last_version {
+                let indexed = {
+                    let mut search_handler = search_handler.lock().unwrap();
+                    let indexed = search_handler.entries(&identifier);
+                    search_handler.remove(&identifier);
+                    indexed
+                };
+                rollback.push(|| {
+                    let mut search_handler = search_handler.lock().unwrap();
+                    search_handler.restore(&identifier, indexed);
+                    let _ = search_handler.persist();
+                });
+                search_handler.lock().unwrap().persist()?;
+            }
+
+            Ok(())
+        })();
+
+        if let Err(e) = committed {
+            rollback.run();
+            return Err(e);
+        }
+
+        Ok(())
+    }).await
+}
+
+// Problems with the parents a runtime model declares, checked against what is published.
+fn check_parents(
+    runtime_model: &ExtensionRuntimeModel,
+    metadata_handler: &MetadataHandler,
+) -> HttpResult<Vec<String>> {
+    let mut problems = Vec::new();
+
+    for declared in &runtime_model.parents {
+        let Some(parent) = resolve_parent(declared, metadata_handler) else {
+            problems.push(format!(
+                "No published version of parent '{}:{}' matches '{}'.",
+                declared.group, declared.extension, declared.version
+            ));
+            continue;
+        };
+
+        if !metadata_handler.has_version(&parent.identifier(), &parent.version) {
+            problems.push(format!("Parent '{}' has not been published.", parent.as_key()));
+        } else if metadata_handler.is_yanked(&parent.identifier(), &parent.version) {
+            problems.push(format!("Parent '{}' has been yanked.", parent.as_key()));
+        }
+    }
+
+    for cycle in introduced_cycles(Path::new("static"), runtime_model, metadata_handler)? {
+        problems.push(format!("The parents close the cycle {}.", cycle.join(" -> ")));
+    }
+
+    Ok(problems)
 }
 
 impl From<ZipError> for HandlerError {
@@ -123,10 +459,25 @@ impl From<ZipError> for HandlerError {
     }
 }
 
-async fn build_bundle_from(
-    read: impl Read + Seek
-) -> HttpResult<ExtensionBundle<Cursor<Vec<u8>>>> {
+// Runs synchronous bundle IO on a blocking worker so uploads do not stall the async executor.
+async fn blocking<T: Send + 'static>(
+    task: impl FnOnce() -> HttpResult<T> + Send + 'static
+) -> HttpResult<T> {
+    tokio::task::spawn_blocking(task).await.map_err(|e| {
+        HandlerError::server_error(
+            "Internal server error".into(),
+            Some(e.to_string()),
+        )
+    })?
+}
+
+// Only erm.json and metadata.json are read here, every other entry stays in the archive until it is written.
+fn build_bundle_from<R: Read + Seek>(
+    read: R,
+    limits: &BundleLimits,
+) -> HttpResult<ExtensionBundle<R>> {
     let mut zip = ZipArchive::new(read)?;
+    check_entries(&mut zip, limits)?;
 
     let runtime_model = zip.by_name("erm.json").map_err(|e| {
         if let ZipError::FileNotFound = e {
@@ -157,28 +508,203 @@ async fn build_bundle_from(
         )
     })?;
 
+    let files = (0..zip.len())
+        .filter_map(|it| zip.name_for_index(it).map(|name| name.to_string()))
+        .collect();
+
     Ok(ExtensionBundle {
+        file_version: runtime_model.version.clone(),
         runtime_model,
         metadata,
-        files: (0..zip.len()).map(|it| {
-            let mut file = zip.by_index(it)?;
-            let mut cursor = Cursor::new(Vec::new());
+        archive: zip,
+        files,
+    })
+}
+
+fn invalid_entry(details: String) -> HandlerError {
+    HandlerError::new(
+        "Invalid extension bundle".into(),
+        Some(details),
+        Status::BadRequest,
+    )
+}
 
-            io::copy(&mut file, &mut cursor)?;
+// Entries below this size are never treated as compression bombs, small text files legitimately compress very well.
+const COMPRESSION_RATIO_MIN_SIZE: u64 = 1024 * 1024;
 
-            Ok::<(Cursor<Vec<u8>>, String), HandlerError>((cursor, file.name().to_string()))
-        }).collect::<HttpResult<Vec<_>>>()?,
-    })
+// Checks the central directory of the bundle against the limits before any entry is decompressed.
+// Bundles must be flat, an entry name is joined onto the version directory when it is written.
+fn check_entries<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    limits: &BundleLimits,
+) -> HttpResult<()> {
+    if zip.len() > limits.max_entries {
+        return Err(invalid_entry(format!(
+            "The bundle contains {} entries, at most {} are allowed.",
+            zip.len(), limits.max_entries
+        )));
+    }
+
+    let mut total_size: u64 = 0;
+    let mut names = HashSet::new();
+    for index in 0..zip.len() {
+        let entry = zip.by_index_raw(index)?;
+        let name = entry.name();
+
+        if name.is_empty()
+            || name.starts_with('/')
+            || name.starts_with('\\')
+            || name.contains('\0')
+            || name.split(['/', '\\']).any(|it| it == "..")
+            || entry.enclosed_name().is_none() {
+            return Err(invalid_entry(format!("Entry '{}' has an illegal path.", name)));
+        }
+
+        if entry.is_dir() || name.contains(['/', '\\']) || name == "." {
+            return Err(invalid_entry(format!(
+                "Entry '{}' is a directory or inside one, bundle entries must all be at the root.",
+                name
+            )));
+        }
+
+        // Which of two entries with the same name gets written would be up to the archive reader.
+        if !names.insert(name.to_string()) {
+            return Err(invalid_entry(format!("Entry '{}' appears more than once.", name)));
+        }
+
+        if entry.size() > limits.max_entry_size {
+            return Err(invalid_entry(format!(
+                "Entry '{}' decompresses to {} bytes, at most {} are allowed.",
+                name, entry.size(), limits.max_entry_size
+            )));
+        }
+
+        if entry.size() > COMPRESSION_RATIO_MIN_SIZE
+            && entry.size() / entry.compressed_size().max(1) > limits.max_compression_ratio {
+            return Err(invalid_entry(format!(
+                "Entry '{}' has a suspicious compression ratio, at most {}:1 is allowed.",
+                name, limits.max_compression_ratio
+            )));
+        }
+
+        total_size += entry.size();
+        if total_size > limits.max_total_size {
+            return Err(invalid_entry(format!(
+                "The bundle decompresses to more than {} bytes.",
+                limits.max_total_size
+            )));
+        }
+    }
+
+    Ok(())
 }
 
-fn validate_bundle<'a>(
-    extension_bundle: &ExtensionBundle<impl Read>
+fn validate_bundle(
+    extension_bundle: &mut ExtensionBundle<impl Read + Seek>
 ) -> HttpResult<()> {
+    // Checked before anything else, every path the bundle is written to is built from these.
+    let runtime_model = &extension_bundle.runtime_model;
+    check_coordinates(&runtime_model.group_id, &runtime_model.name, &runtime_model.version)?;
     VersionType::classify(&extension_bundle.runtime_model.version)?;
+    Version::parse_or_reject(&extension_bundle.runtime_model.version)?;
+
+    let mut violations: Vec<String> = Vec::new();
+
+    for partition in &extension_bundle.runtime_model.partitions {
+        let jar_name = format!("{}.jar", partition.name);
+        if !extension_bundle.files.contains(&jar_name) {
+            violations.push(format!(
+                "Partition '{}' is declared in the ERM but '{}' is missing from the bundle.",
+                partition.name, jar_name
+            ));
+        }
+
+        let json_name = format!("{}.json", partition.name);
+        if !extension_bundle.files.contains(&json_name) {
+            violations.push(format!(
+                "Partition '{}' is declared in the ERM but '{}' is missing from the bundle.",
+                partition.name, json_name
+            ));
+            continue;
+        }
+
+        let prm_file = extension_bundle.archive.by_name(&json_name)?;
+        match serde_json::from_reader::<_, PartitionRuntimeModel>(prm_file) {
+            Ok(prm) => {
+                if prm.name != partition.name {
+                    violations.push(format!(
+                        "'{}' declares the name '{}' but the ERM declares '{}'.",
+                        json_name, prm.name, partition.name
+                    ));
+                }
+                if prm.r#type != partition.r#type {
+                    violations.push(format!(
+                        "'{}' declares the type '{}' but the ERM declares '{}'.",
+                        json_name, prm.r#type, partition.r#type
+                    ));
+                }
+            }
+            Err(e) => violations.push(format!(
+                "'{}' is not a valid partition runtime model: {}",
+                json_name, e
+            )),
+        }
+    }
+
+    for parent in &extension_bundle.runtime_model.parents {
+        if VersionRange::parse(&parent.version).is_none() {
+            violations.push(format!(
+                "Parent '{}:{}' declares '{}', which is neither a version nor a version range.",
+                parent.group, parent.extension, parent.version
+            ));
+        }
+    }
+
+    if extension_bundle.files.iter().any(|it| it == MANIFEST_ENTRY) {
+        violations.push(format!("'{}' is reserved for the repository.", MANIFEST_ENTRY));
+    }
+
+    for name in &extension_bundle.files {
+        let signed = name.strip_suffix(&format!(".{}", REPOSITORY_SIGNATURE_EXTENSION))
+            .filter(|it| extension_bundle.files.iter().any(|file| file == it));
+
+        if let Some(signed) = signed {
+            violations.push(format!("'{}' is reserved for the repository's signature of '{}'.", name, signed));
+        }
+    }
+
+    // Partitions not declared in the ERM are never published, so they are dropped rather than rejected.
+    let declared: Vec<&str> = extension_bundle.runtime_model.partitions.iter()
+        .map(|it| it.name.as_str())
+        .collect();
+    extension_bundle.files.retain(|name| {
+        partition_name(name).is_none_or(|it| declared.contains(&it))
+    });
+
+    if !violations.is_empty() {
+        return Err(HandlerError::new(
+            "Invalid extension bundle".into(),
+            Some(violations.join("\n")),
+            Status::BadRequest,
+        ));
+    }
 
     Ok(())
 }
 
+// Returns the partition a bundle file belongs to, or None if the file is not a partition file.
+// Checksums of a partition file belong to the same partition.
+fn partition_name(file_name: &str) -> Option<&str> {
+    let file_name = sidecar_target(file_name).map_or(file_name, |(target, _)| target);
+
+    if file_name == "erm.json" || file_name == "metadata.json" {
+        return None;
+    }
+
+    file_name.strip_suffix(".jar")
+        .or_else(|| file_name.strip_suffix(".json"))
+}
+
 impl From<io::Error> for HandlerError {
     fn from(value: io::Error) -> Self {
         HandlerError::server_error(
@@ -188,61 +714,412 @@ impl From<io::Error> for HandlerError {
     }
 }
 
-async fn write_bundle(
-    path: PathBuf,
+// The name a bundle entry is stored under in the version directory, maven style.
+fn published_name(bundle: &ExtensionBundle<impl Read + Seek>, entry_name: &str) -> String {
+    if !entry_name.starts_with(".") {
+        format!(
+            "{}-{}-{}",
+            bundle.runtime_model.name,
+            bundle.file_version,
+            entry_name,
+        )
+    } else {
+        format!(
+            "{}-{}{}",
+            bundle.runtime_model.name,
+            bundle.file_version,
+            entry_name
+        )
+    }
+}
+
+// Writes the checksum sidecars of a published file, and the repository's signature of its SHA-256 digest as `.sig`.
+fn write_sidecars(
+    path: &Path,
+    file_name: &str,
+    checksums: &Checksums,
+    signer: &RepositorySigner,
+) -> io::Result<()> {
+    for extension in SIDECAR_EXTENSIONS {
+        std::fs::write(
+            path.join(format!("{}.{}", file_name, extension)),
+            checksums.by_extension(extension).unwrap(),
+        )?;
+    }
+
+    let digest = hex::decode(&checksums.sha256).unwrap();
+    std::fs::write(
+        path.join(format!("{}.{}", file_name, REPOSITORY_SIGNATURE_EXTENSION)),
+        signer.sign(&digest),
+    )
+}
+
+// A checksum shipped in the bundle for one of its other entries.
+struct ExpectedChecksum {
+    sidecar: String,
+    target: String,
+    extension: &'static str,
+    digest: String,
+}
+
+fn read_expected_checksums(
+    bundle: &mut ExtensionBundle<impl Read + Seek>
+) -> HttpResult<Vec<ExpectedChecksum>> {
+    let mut expected = Vec::new();
+
+    for name in bundle.files.iter() {
+        let Some((target, extension)) = sidecar_target(name) else {
+            continue;
+        };
+
+        // A digest is never more than a line, anything longer is not a checksum file.
+        let mut content = String::new();
+        bundle.archive.by_name(name)?
+            .take(1024)
+            .read_to_string(&mut content)
+            .map_err(|_| invalid_entry(format!("Checksum '{}' is not valid text.", name)))?;
+
+        expected.push(ExpectedChecksum {
+            sidecar: name.clone(),
+            target: target.to_string(),
+            extension,
+            digest: parse_sidecar(&content)
+                .ok_or_else(|| invalid_entry(format!("Checksum '{}' is empty.", name)))?,
+        });
+    }
+
+    Ok(expected)
+}
+
+fn verify_checksums(
+    bundle: &ExtensionBundle<impl Read + Seek>,
+    expected: &[ExpectedChecksum],
+    checksums: &HashMap<String, Checksums>,
+) -> HttpResult<()> {
+    let violations: Vec<String> = expected.iter().filter_map(|it| {
+        let Some(actual) = checksums.get(&published_name(bundle, &it.target)) else {
+            return Some(format!("'{}' is a checksum for '{}', which is not published by this bundle.", it.sidecar, it.target));
+        };
+        let actual = actual.by_extension(it.extension).unwrap();
+
+        (*actual != it.digest).then(|| format!(
+            "'{}' does not match the content of '{}', expected {} but was {}.",
+            it.sidecar, it.target, it.digest, actual
+        ))
+    }).collect();
+
+    if !violations.is_empty() {
+        return Err(HandlerError::new(
+            "Checksum mismatch".into(),
+            Some(violations.join("\n")),
+            Status::BadRequest,
+        ));
+    }
+
+    Ok(())
+}
+
+// Writes every published entry alongside .sha256, .sha1 and .md5 sidecars, returning the digests by file name.
+// Checksum entries shipped in the bundle are verified against what was written, then replaced by the computed sidecars.
+fn write_bundle(
+    path: &Path,
+    bundle: &mut ExtensionBundle<impl Read + Seek>,
+    limits: &BundleLimits,
+    signer: &RepositorySigner,
+) -> HttpResult<HashMap<String, Checksums>> {
+    let expected = read_expected_checksums(bundle)?;
+
+    let mut total_size: u64 = 0;
+    let mut checksums = HashMap::new();
+
+    for name in bundle.files.iter().filter(|it| !is_sidecar(it)) {
+        let file_name = published_name(bundle, name);
+        let entry = bundle.archive.by_name(name)?;
+        let mut file = ChecksumWriter::new(File::create(path.join(&file_name))?);
+
+        // Sizes in the central directory were checked already, but they are not trusted while decompressing.
+        let limit = limits.max_entry_size.min(limits.max_total_size - total_size);
+        let written = io::copy(&mut entry.take(limit + 1), &mut file)?;
+        if written > limit {
+            return Err(invalid_entry(format!(
+                "Entry '{}' decompresses to more data than it declares.",
+                name
+            )));
+        }
+        total_size += written;
+
+        let (_, file_checksums) = file.finish();
+        write_sidecars(path, &file_name, &file_checksums, signer)?;
+
+        checksums.insert(file_name, file_checksums);
+    }
+
+    verify_checksums(bundle, &expected, &checksums)?;
+
+    Ok(checksums)
+}
+
+// Checks the publisher signature over the manifest of every bundle entry, then publishes the manifest next to it.
+// Entries that were written already are not read again, their digest was computed while writing them.
+fn verify_signature(
+    path: &Path,
     bundle: &mut ExtensionBundle<impl Read + Seek>,
+    checksums: &mut HashMap<String, Checksums>,
+    publisher: Option<&PublisherKeys>,
+    limits: &BundleLimits,
+    signer: &RepositorySigner,
 ) -> HttpResult<()> {
-    create_dir_all(&path).await?;
-
-    for (read, name) in bundle.files.iter_mut() {
-        let file_path =
-            path.join(
-                if !name.starts_with(".") {
-                    format!(
-                        "{}-{}-{}",
-                        bundle.runtime_model.name,
-                        bundle.runtime_model.version,
-                        name,
-                    )
-                } else {
-                    format!(
-                        "{}-{}{}",
-                        bundle.runtime_model.name,
-                        bundle.runtime_model.version,
-                        name
-                    )
-                }
-            );
+    if !bundle.files.iter().any(|it| it == SIGNATURE_ENTRY) {
+        return Ok(());
+    }
+
+    let Some(publisher) = publisher.filter(|it| !it.keys.is_empty()) else {
+        return Err(HandlerError::new(
+            "Invalid bundle signature".into(),
+            Some(format!("No publisher keys are registered for '{}'.", bundle.runtime_model.group_id)),
+            Status::BadRequest,
+        ));
+    };
+    let keys = publisher.keys.iter()
+        .map(|it| parse_key(it).ok_or_else(|| HandlerError::server_error(
+            "Internal server error".into(),
+            Some(format!("Invalid publisher key registered for '{}'.", bundle.runtime_model.group_id)),
+        )))
+        .collect::<HttpResult<Vec<_>>>()?;
 
-        read.seek(SeekFrom::Start(0)).expect("Failed to seek");
-        let mut contents: Vec<u8> = Vec::new();
-        read.read_to_end(&mut contents)?;
+    let mut digests = BTreeMap::new();
+    for index in 0..bundle.archive.len() {
+        let name = bundle.archive.name_for_index(index).unwrap().to_string();
+        if name == SIGNATURE_ENTRY {
+            continue;
+        }
 
-        std::fs::write(file_path, contents)?;
+        let digest = match checksums.get(&published_name(bundle, &name)) {
+            Some(checksums) => checksums.sha256.clone(),
+            None => {
+                let mut hasher = ChecksumWriter::new(io::sink());
+                io::copy(&mut bundle.archive.by_index(index)?.take(limits.max_entry_size), &mut hasher)?;
+
+                hasher.finish().1.sha256
+            }
+        };
+        digests.insert(name, digest);
     }
+    let manifest = manifest(&digests);
+
+    let mut signature = String::new();
+    bundle.archive.by_name(SIGNATURE_ENTRY)?
+        .take(1024)
+        .read_to_string(&mut signature)
+        .map_err(|_| invalid_entry(format!("'{}' is not valid text.", SIGNATURE_ENTRY)))?;
+
+    let verified = parse_signature(&signature)
+        .is_some_and(|it| verify(&keys, manifest.as_bytes(), &it));
+    if !verified {
+        return Err(HandlerError::new(
+            "Invalid bundle signature".into(),
+            Some(format!(
+                "'{}' is not a valid signature of the bundle by a publisher registered for '{}'.",
+                SIGNATURE_ENTRY, bundle.runtime_model.group_id
+            )),
+            Status::BadRequest,
+        ));
+    }
+
+    let file_name = published_name(bundle, MANIFEST_ENTRY);
+    let mut file = ChecksumWriter::new(File::create(path.join(&file_name))?);
+    file.write_all(manifest.as_bytes())?;
+
+    let (_, manifest_checksums) = file.finish();
+    write_sidecars(path, &file_name, &manifest_checksums, signer)?;
+    checksums.insert(file_name, manifest_checksums);
 
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::fs::{File, read, exists};
     use std::fs;
-    use std::io::Write;
+    use std::io::{Cursor, Write};
     use std::ops::Deref;
     use std::path::{Path, PathBuf};
     use std::sync::{Arc, Mutex};
 
-    use rocket::{Request, uri};
-    use rocket::http::Header;
+    use rocket::uri;
+    use rocket::http::{Header, Status};
+    use rocket::local::asynchronous::Client;
+    use ed25519_dalek::{Signer, SigningKey};
+    use sha2::{Digest, Sha256};
     use zip::write::SimpleFileOptions;
-    use zip::ZipWriter;
+    use zip::{ZipArchive, ZipWriter};
 
-    use crate::auth::Authorizer;
+    use crate::config::{BundleLimits, ParentPolicy, PublisherKeys, ServerConfig, SnapshotConfig};
     use crate::metadata::MetadataHandler;
-    use crate::route::registry::ExtensionFileServer;
+    use crate::ownership::OwnershipStore;
+    use crate::route::registry::{build_bundle_from, validate_bundle, ExtensionFileServer};
+    use crate::route::testing::test_rocket;
     use crate::search::search::SearchHandler;
-    use crate::types::{ExtensionIdentifier, ExtensionMetadata, ExtensionRuntimeModel, PartitionRuntimeModel};
+    use crate::signature::{manifest, parse_key, parse_signature, verify, RepositorySigner, SIGNATURE_ENTRY};
+    use crate::types::{ExtensionBundle, ExtensionIdentifier, ExtensionMetadata, ExtensionParent, ExtensionRuntimeModel, PartitionRuntimeModel, PublishResponse};
+
+    fn make_bundle(
+        partitions: Vec<PartitionRuntimeModel>,
+        files: Vec<(&str, Vec<u8>)>,
+    ) -> ExtensionBundle<Cursor<Vec<u8>>> {
+        ExtensionBundle {
+            runtime_model: ExtensionRuntimeModel {
+                api_version: 0,
+                group_id: "com.example".into(),
+                name: "fishmonger".into(),
+                version: "1.0".into(),
+                repositories: vec![],
+                parents: vec![],
+                partitions,
+                attributes: Default::default(),
+            },
+            metadata: ExtensionMetadata {
+                name: "Fish Monger".into(),
+                developers: vec![],
+                icon: None,
+                description: "".into(),
+                tags: vec![],
+                app: "test".into(),
+            },
+            files: files.iter().map(|(name, _)| name.to_string()).collect(),
+            file_version: "1.0".into(),
+            archive: ZipArchive::new(Cursor::new(zip_of(files))).unwrap(),
+        }
+    }
+
+    fn zip_of(files: Vec<(&str, Vec<u8>)>) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+        for (name, content) in files {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.deref()).unwrap();
+        }
+
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn make_prm(name: &str, r#type: &str) -> PartitionRuntimeModel {
+        PartitionRuntimeModel {
+            r#type: r#type.into(),
+            name: name.into(),
+            repositories: vec![],
+            dependencies: vec![],
+            options: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_validate_bundle_drops_undeclared_partitions() {
+        let mut bundle = make_bundle(
+            vec![make_prm("test1", "test")],
+            vec![
+                ("erm.json", b"{}".to_vec()),
+                ("metadata.json", b"{}".to_vec()),
+                ("test1.jar", b"jar".to_vec()),
+                ("test1.json", serde_json::to_vec(&make_prm("test1", "test")).unwrap()),
+                ("test1.jar.sha256", b"checksum".to_vec()),
+                ("test2.jar", b"jar".to_vec()),
+                ("test2.json", serde_json::to_vec(&make_prm("test2", "test")).unwrap()),
+                ("test2.jar.sha256", b"checksum".to_vec()),
+            ],
+        );
+
+        assert!(validate_bundle(&mut bundle).is_ok());
+
+        let names: Vec<&str> = bundle.files.iter().map(|name| name.as_str()).collect();
+        assert_eq!(names, vec!["erm.json", "metadata.json", "test1.jar", "test1.json", "test1.jar.sha256"]);
+    }
+
+    #[test]
+    fn test_validate_bundle_rejects_broken_partitions() {
+        let mut bundle = make_bundle(
+            vec![make_prm("test1", "test"), make_prm("test2", "test"), make_prm("test3", "test")],
+            vec![
+                ("test1.json", serde_json::to_vec(&make_prm("test1", "test")).unwrap()),
+                ("test2.jar", b"jar".to_vec()),
+                ("test2.json", serde_json::to_vec(&make_prm("other", "main")).unwrap()),
+                ("test3.jar", b"jar".to_vec()),
+                ("test3.json", b"not json".to_vec()),
+            ],
+        );
+
+        assert!(validate_bundle(&mut bundle).is_err());
+    }
+
+    fn make_erm(name: &str, version: &str) -> ExtensionRuntimeModel {
+        ExtensionRuntimeModel {
+            api_version: 0,
+            group_id: "com.example".into(),
+            name: name.into(),
+            version: version.into(),
+            repositories: vec![],
+            parents: vec![],
+            partitions: vec![make_prm("test1", "test")],
+            attributes: Default::default(),
+        }
+    }
+
+    // Builds a bundle in memory, every partition in the ERM gets a PRM and jar alongside any extra files.
+    fn make_zip_bytes(erm: &ExtensionRuntimeModel, extra: Vec<(&str, Vec<u8>)>) -> Vec<u8> {
+        let entries = make_entries(erm);
+
+        let mut files: Vec<(&str, Vec<u8>)> = entries.iter()
+            .map(|(name, content)| (name.as_str(), content.clone()))
+            .collect();
+        files.extend(extra);
+
+        zip_of(files)
+    }
+
+    fn make_entries(erm: &ExtensionRuntimeModel) -> Vec<(String, Vec<u8>)> {
+        let metadata = ExtensionMetadata {
+            name: erm.name.clone(),
+            developers: vec![],
+            icon: None,
+            description: "A test extension".into(),
+            tags: vec![],
+            app: "test".into(),
+        };
+
+        let mut files: Vec<(String, Vec<u8>)> = vec![
+            ("erm.json".into(), serde_json::to_vec(erm).unwrap()),
+            ("metadata.json".into(), serde_json::to_vec(&metadata).unwrap()),
+        ];
+        for partition in &erm.partitions {
+            files.push((format!("{}.json", partition.name), serde_json::to_vec(partition).unwrap()));
+            files.push((format!("{}.jar", partition.name), format!("Not a jar for {}", partition.name).into_bytes()));
+        }
+
+        files
+    }
+
+    #[test]
+    fn test_build_bundle_rejects_unsafe_entries() {
+        let limits = BundleLimits::default();
+        let erm = make_erm("unsafe", "1.0");
+
+        assert!(build_bundle_from(Cursor::new(make_zip_bytes(&erm, vec![])), &limits).is_ok());
+
+        for name in ["../test1.jar", "/test1.jar", "nested/test1.jar", "nested/", "..\\test1.jar"] {
+            let bundle = make_zip_bytes(&erm, vec![(name, b"content".to_vec())]);
+            assert!(build_bundle_from(Cursor::new(bundle), &limits).is_err(), "{} was accepted", name);
+        }
+
+        let bomb = make_zip_bytes(&erm, vec![("bomb.bin", vec![0u8; 4 * 1024 * 1024])]);
+        assert!(build_bundle_from(Cursor::new(bomb), &limits).is_err());
+
+        let limits = BundleLimits {
+            max_entries: 3,
+            ..BundleLimits::default()
+        };
+        assert!(build_bundle_from(Cursor::new(make_zip_bytes(&erm, vec![])), &limits).is_err());
+    }
 
     async fn make_zip() -> PathBuf {
         let partition_test1_prm = PartitionRuntimeModel {
@@ -298,7 +1175,7 @@ mod tests {
             zip.finish().unwrap();
         }
 
-        return PathBuf::from("test/test.zip");
+        PathBuf::from("test/test.zip")
     }
 
     #[tokio::test]
@@ -313,34 +1190,457 @@ mod tests {
 
     #[tokio::test]
     async fn test_put_bundle() {
-        struct TestAuthorizer;
-
-        impl Authorizer for TestAuthorizer {
-            fn is_authorized(&self, request: &Request, token: &str) -> bool {
-                return true;
-            }
-        }
-
         let zip_resource = make_zip().await;
 
-        let client = rocket::local::asynchronous::Client::tracked(
-            rocket::build()
-                .mount("/", ExtensionFileServer)
-                .manage(Arc::new(Mutex::new(Box::new(TestAuthorizer) as Box<dyn Authorizer>)))
-                .manage(MetadataHandler::hydrate_cache("config/metadata.json").unwrap())
-                .manage(Arc::new(Mutex::new(SearchHandler::<ExtensionIdentifier>::hydrate_cache("config/search_index.json").unwrap())))
-        ).await.unwrap();
+        let client = make_client().await;
 
-        let r = client.put(uri!(super::put_object))
+        let r = client.put(uri!(super::put_object(_)))
             .header(Header::new("Authorization", "Bearer nothing"))
             .body(read(zip_resource).unwrap())
             .dispatch().await;
 
         println!("{}", r.into_string().await.unwrap_or("no body".to_string()));
         let metadata_handler: &MetadataHandler = client.rocket().state().unwrap();
-        metadata_handler.persist_to("config/metadata.json").unwrap();
+        metadata_handler.checkpoint().unwrap();
 
         let search_handler: &Arc<Mutex<SearchHandler<ExtensionIdentifier>>> = client.rocket().state().unwrap();
-        search_handler.lock().unwrap().persist_to("config/search_index.json").unwrap();
+        search_handler.lock().unwrap().persist().unwrap();
+    }
+
+    // Every test starts from an empty repository of its own, see `test_rocket`.
+    async fn make_client() -> Client {
+        make_client_with(ServerConfig::default()).await
+    }
+
+    async fn make_client_with(config: ServerConfig) -> Client {
+        Client::tracked(
+            test_rocket()
+                .mount("/", ExtensionFileServer)
+                .manage(config)
+        ).await.unwrap()
+    }
+
+    // Publishes with an admin overwrite so that files left behind by a previous run never conflict.
+    async fn publish(client: &Client, bundle: Vec<u8>) -> Status {
+        client.put(uri!(super::put_object(Some(true))))
+            .header(Header::new("Authorization", "Bearer admin"))
+            .body(bundle)
+            .dispatch().await
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_republish_requires_admin_overwrite() {
+        let bundle = make_zip_bytes(&make_erm("immutable", "1.0"), vec![]);
+        let client = make_client().await;
+
+        assert_eq!(publish(&client, bundle.clone()).await, Status::Ok);
+
+        let r = client.put(uri!(super::put_object(_)))
+            .header(Header::new("Authorization", "Bearer nothing"))
+            .body(bundle.clone())
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Conflict);
+
+        let r = client.put(uri!(super::put_object(Some(true))))
+            .header(Header::new("Authorization", "Bearer nothing"))
+            .body(bundle.clone())
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Forbidden);
+
+        let r = client.put(uri!(super::put_object(Some(true))))
+            .header(Header::new("Authorization", "Bearer admin"))
+            .body(bundle.clone())
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+
+        let metadata_handler: &MetadataHandler = client.rocket().state().unwrap();
+        let (_, _, versions) = metadata_handler.get_managed_metadata(&ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "immutable".into(),
+        }).unwrap();
+        assert_eq!(versions, vec!["1.0".to_string()]);
+
+        // 1.0.0 is the same version written differently, even an overwrite would keep both.
+        let bundle = make_zip_bytes(&make_erm("immutable", "1.0.0"), vec![]);
+        assert_eq!(publish(&client, bundle).await, Status::Conflict);
+        let (_, _, versions) = metadata_handler.get_managed_metadata(&ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "immutable".into(),
+        }).unwrap();
+        assert_eq!(versions, vec!["1.0".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_put_bundle_writes_checksums() {
+        let client = make_client().await;
+        assert_eq!(publish(&client, make_zip_bytes(&make_erm("checksums", "1.0"), vec![])).await, Status::Ok);
+
+        let metadata_handler: &MetadataHandler = client.rocket().state().unwrap();
+        let checksums = metadata_handler.checksums(&ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "checksums".into(),
+        }, "1.0");
+        let jar_checksums = checksums.get("checksums-1.0-test1.jar").unwrap();
+
+        let r = client.get("/registry/com/example/checksums/1.0/checksums-1.0-test1.jar.sha256")
+            .dispatch().await;
+        assert_eq!(r.into_string().await.unwrap(), jar_checksums.sha256);
+
+        let r = client.get("/registry/com/example/checksums/1.0/checksums-1.0-test1.jar.md5")
+            .dispatch().await;
+        assert_eq!(r.into_string().await.unwrap(), jar_checksums.md5);
+
+        let signer: &RepositorySigner = client.rocket().state().unwrap();
+        let r = client.get("/registry/com/example/checksums/1.0/checksums-1.0-test1.jar.sig")
+            .dispatch().await;
+        let signature = parse_signature(&r.into_string().await.unwrap()).unwrap();
+        let digest = hex::decode(&jar_checksums.sha256).unwrap();
+        assert!(verify(&[parse_key(&signer.public_key()).unwrap()], &digest, &signature));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_put_bundle_verifies_client_checksums() {
+        let client = make_client().await;
+        let erm = make_erm("client_checksums", "1.0");
+
+        let jar_sha1 = "ee1e6a0d6fa87785327096e35747775f67873578";
+        let bundle = make_zip_bytes(&erm, vec![
+            ("test1.jar.sha1", format!("{}  test1.jar\n", jar_sha1.to_uppercase()).into_bytes()),
+        ]);
+        assert_eq!(publish(&client, bundle).await, Status::Ok);
+
+        let r = client.get("/registry/com/example/client_checksums/1.0/client_checksums-1.0-test1.jar.sha1")
+            .dispatch().await;
+        assert_eq!(r.into_string().await.unwrap(), jar_sha1);
+
+        let bundle = make_zip_bytes(&erm, vec![
+            ("test1.jar.sha256", b"0000000000000000000000000000000000000000000000000000000000000000".to_vec()),
+        ]);
+        assert_eq!(publish(&client, bundle).await, Status::BadRequest);
+
+        // The failed overwrite is rolled back, the version published before it is untouched.
+        let r = client.get("/registry/com/example/client_checksums/1.0/client_checksums-1.0-test1.jar.sha1")
+            .dispatch().await;
+        assert_eq!(r.into_string().await.unwrap(), jar_sha1);
+        let metadata_handler: &MetadataHandler = client.rocket().state().unwrap();
+        assert!(metadata_handler.has_version(&ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "client_checksums".into(),
+        }, "1.0"));
+
+        let bundle = make_zip_bytes(&erm, vec![
+            ("notes.txt.md5", b"00000000000000000000000000000000".to_vec()),
+        ]);
+        assert_eq!(publish(&client, bundle).await, Status::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_put_bundle_verifies_signature() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let config = ServerConfig {
+            publishers: HashMap::from([("com.example".to_string(), PublisherKeys {
+                keys: vec![hex::encode(signing_key.verifying_key().to_bytes())],
+                require_signature: true,
+            })]),
+            ..ServerConfig::default()
+        };
+        let client = make_client_with(config).await;
+        let erm = make_erm("signature", "1.0");
+
+        assert_eq!(publish(&client, make_zip_bytes(&erm, vec![])).await, Status::BadRequest);
+
+        let manifest = manifest(&make_entries(&erm).into_iter()
+            .map(|(name, content)| (name, format!("{:x}", Sha256::digest(content))))
+            .collect());
+        let signature = hex::encode(signing_key.sign(manifest.as_bytes()).to_bytes());
+
+        let bundle = make_zip_bytes(&erm, vec![(SIGNATURE_ENTRY, signature.clone().into_bytes())]);
+        assert_eq!(publish(&client, bundle).await, Status::Ok);
+
+        let r = client.get("/registry/com/example/signature/1.0/signature-1.0-bundle.manifest")
+            .dispatch().await;
+        assert_eq!(r.into_string().await.unwrap(), manifest);
+
+        let r = client.get("/registry/com/example/signature/1.0/signature-1.0-bundle.sig")
+            .dispatch().await;
+        assert_eq!(r.into_string().await.unwrap(), signature);
+
+        // Any entry added after signing invalidates the signature.
+        let bundle = make_zip_bytes(&erm, vec![
+            (SIGNATURE_ENTRY, signature.into_bytes()),
+            ("notes.txt", b"unsigned".to_vec()),
+        ]);
+        assert_eq!(publish(&client, bundle).await, Status::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_yank_and_delete_version() {
+        let client = make_client().await;
+        let identifier = ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "yank".into(),
+        };
+
+        assert_eq!(publish(&client, make_zip_bytes(&make_erm("yank", "1.0"), vec![])).await, Status::Ok);
+        assert_eq!(publish(&client, make_zip_bytes(&make_erm("yank", "1.1"), vec![])).await, Status::Ok);
+
+        let r = client.post("/registry/com.example:yank:1.1/yank")
+            .header(Header::new("Authorization", "Bearer nothing"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+
+        let metadata_handler: &MetadataHandler = client.rocket().state().unwrap();
+        let (_, latest, _) = metadata_handler.get_managed_metadata(&identifier).unwrap();
+        assert_eq!(latest.release, Some("1.0".to_string()));
+        assert!(metadata_handler.is_yanked(&identifier, "1.1"));
+
+        // Yanked versions are still downloadable.
+        let r = client.get("/registry/com/example/yank/1.1/yank-1.1-test1.jar").dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+
+        let r = client.delete("/registry/com.example:yank:1.1/yank")
+            .header(Header::new("Authorization", "Bearer nothing"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+        let (_, latest, _) = metadata_handler.get_managed_metadata(&identifier).unwrap();
+        assert_eq!(latest.release, Some("1.1".to_string()));
+
+        let r = client.delete("/registry/com.example:yank:1.1")
+            .header(Header::new("Authorization", "Bearer nothing"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Forbidden);
+
+        let r = client.delete("/registry/com.example:yank:1.1")
+            .header(Header::new("Authorization", "Bearer admin"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+        assert!(!Path::new("static/com/example/yank/1.1").exists());
+
+        let (_, latest, versions) = metadata_handler.get_managed_metadata(&identifier).unwrap();
+        assert_eq!(latest.release, Some("1.0".to_string()));
+        assert_eq!(versions, vec!["1.0".to_string()]);
+
+        let r = client.post("/registry/com.example:yank:1.1/yank")
+            .header(Header::new("Authorization", "Bearer nothing"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_traversal_in_coordinates_is_rejected() {
+        let _ = fs::remove_dir_all("static/com/escape_probe");
+        let client = make_client().await;
+
+        // Joined into a path this would leave com/example for com/escape_probe, which the token does not cover.
+        let r = client.put(uri!(super::put_object(_)))
+            .header(Header::new("Authorization", "Bearer com.example"))
+            .body(make_zip_bytes(&make_erm("../escape_probe", "1.0"), vec![]))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::BadRequest);
+        assert!(!Path::new("static/com/escape_probe").exists());
+
+        let r = client.put(uri!(super::put_object(_)))
+            .header(Header::new("Authorization", "Bearer com.example"))
+            .body(make_zip_bytes(&make_erm("traversal", "1.0+../../escape_probe"), vec![]))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::BadRequest);
+        assert!(!Path::new("static/com/escape_probe").exists());
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_scoped_to_groups() {
+        let _ = fs::remove_dir_all("static/com/example/scoped");
+        let client = make_client().await;
+
+        let r = client.put(uri!(super::put_object(_)))
+            .header(Header::new("Authorization", "Bearer org.other"))
+            .body(make_zip_bytes(&make_erm("scoped", "1.0"), vec![]))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Forbidden);
+        assert!(!Path::new("static/com/example/scoped/1.0").exists());
+
+        // A prefix covers the group itself and the groups below it.
+        let r = client.put(uri!(super::put_object(_)))
+            .header(Header::new("Authorization", "Bearer com"))
+            .body(make_zip_bytes(&make_erm("scoped", "1.0"), vec![]))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+
+        let r = client.post("/registry/com.example:scoped:1.0/yank")
+            .header(Header::new("Authorization", "Bearer org.other"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Forbidden);
+
+        let r = client.post("/registry/com.example:scoped:1.0/yank")
+            .header(Header::new("Authorization", "Bearer com.example"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+
+        let r = client.delete("/registry/com.example:scoped:1.0")
+            .header(Header::new("Authorization", "Bearer com.example"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Forbidden);
+    }
+
+    #[tokio::test]
+    async fn test_first_publisher_owns_the_group() {
+        let _ = fs::remove_dir_all("static/com/example/owned");
+        let _ = fs::remove_dir_all("static/com/example/claimed");
+        let client = make_client().await;
+
+        let r = client.put(uri!(super::put_object(_)))
+            .header(Header::new("Authorization", "Bearer com"))
+            .body(make_zip_bytes(&make_erm("owned", "1.0"), vec![]))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+
+        let ownership: &OwnershipStore = client.rocket().state().unwrap();
+        assert_eq!(ownership.owners("com.example"), Some(vec!["com".to_string()]));
+
+        // Covering the group is not enough once another principal owns it.
+        let r = client.put(uri!(super::put_object(_)))
+            .header(Header::new("Authorization", "Bearer com.example"))
+            .body(make_zip_bytes(&make_erm("claimed", "1.0"), vec![]))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Forbidden);
+        assert!(!Path::new("static/com/example/claimed/1.0").exists());
+
+        ownership.share("com.example", "com.example").unwrap();
+        let r = client.put(uri!(super::put_object(_)))
+            .header(Header::new("Authorization", "Bearer com.example"))
+            .body(make_zip_bytes(&make_erm("claimed", "1.0"), vec![]))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_builds() {
+        // Metadata starts out empty in tests, builds left behind by a previous run would never expire.
+        let _ = fs::remove_dir_all("static/com/example/snapshot");
+
+        let client = make_client_with(ServerConfig {
+            snapshots: SnapshotConfig {
+                retention: 2,
+            },
+            ..Default::default()
+        }).await;
+        let identifier = ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "snapshot".into(),
+        };
+
+        // Snapshots are republished without an overwrite.
+        for _ in 0..3 {
+            let r = client.put(uri!(super::put_object(_)))
+                .header(Header::new("Authorization", "Bearer nothing"))
+                .body(make_zip_bytes(&make_erm("snapshot", "1.0-SNAPSHOT"), vec![]))
+                .dispatch().await;
+            assert_eq!(r.status(), Status::Ok);
+        }
+
+        let metadata_handler: &MetadataHandler = client.rocket().state().unwrap();
+        let builds = metadata_handler.snapshot_builds(&identifier, "1.0-SNAPSHOT");
+        assert_eq!(builds.iter().map(|it| it.build_number).collect::<Vec<_>>(), vec![2, 3]);
+        assert!(builds[1].version.starts_with("1.0-") && builds[1].version.ends_with("-3"));
+
+        let path = Path::new("static/com/example/snapshot/1.0-SNAPSHOT");
+        assert!(path.join(format!("snapshot-{}-test1.jar", builds[0].version)).exists());
+        assert!(path.join(format!("snapshot-{}-test1.jar.sha256", builds[1].version)).exists());
+        assert_eq!(fs::read_dir(path).unwrap().filter(|it| it.as_ref().unwrap().file_name().to_str().unwrap().ends_with("-test1.jar")).count(), 2);
+
+        // The plain snapshot version resolves to the newest build.
+        let r = client.get("/registry/com/example/snapshot/1.0-SNAPSHOT/snapshot-1.0-SNAPSHOT-test1.jar.sha256").dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+        assert_eq!(
+            r.into_string().await.unwrap(),
+            fs::read_to_string(path.join(format!("snapshot-{}-test1.jar.sha256", builds[1].version))).unwrap()
+        );
+
+        let r = client.get(format!("/registry/com/example/snapshot/1.0-SNAPSHOT/snapshot-{}-erm.json", builds[0].version)).dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+    }
+
+    fn make_erm_with_parents(name: &str, version: &str, parents: &[(&str, &str)]) -> ExtensionRuntimeModel {
+        let mut erm = make_erm(name, version);
+        erm.parents = parents.iter().map(|(name, version)| ExtensionParent {
+            group: "com.example".into(),
+            extension: name.to_string(),
+            version: version.to_string(),
+        }).collect();
+
+        erm
+    }
+
+    #[tokio::test]
+    async fn test_parents_are_validated() {
+        let client = make_client().await;
+
+        assert_eq!(publish(&client, make_zip_bytes(&make_erm("parents-core", "1.0"), vec![])).await, Status::Ok);
+        assert_eq!(publish(&client, make_zip_bytes(&make_erm_with_parents("parents-app", "1.0", &[("parents-core", "1.0")]), vec![])).await, Status::Ok);
+        assert_eq!(publish(&client, make_zip_bytes(&make_erm_with_parents("parents-app", "1.1", &[("parents-core", "2.0")]), vec![])).await, Status::BadRequest);
+
+        let metadata_handler: &MetadataHandler = client.rocket().state().unwrap();
+        let dependents = metadata_handler.dependents(&ExtensionIdentifier {
+            group: "com.example".into(),
+            name: "parents-core".into(),
+        });
+        assert_eq!(dependents.iter().map(|it| it.descriptor.as_str()).collect::<Vec<_>>(), vec!["com.example:parents-app:1.0"]);
+
+        let r = client.post("/registry/com.example:parents-core:1.0/yank")
+            .header(Header::new("Authorization", "Bearer nothing"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+        assert_eq!(publish(&client, make_zip_bytes(&make_erm_with_parents("parents-app", "1.1", &[("parents-core", "1.0")]), vec![])).await, Status::BadRequest);
+
+        // Ranges only resolve to versions that are not yanked.
+        assert_eq!(publish(&client, make_zip_bytes(&make_erm_with_parents("parents-app", "1.1", &[("parents-core", "^1.0")]), vec![])).await, Status::BadRequest);
+        assert_eq!(publish(&client, make_zip_bytes(&make_erm("parents-core", "1.2"), vec![])).await, Status::Ok);
+        assert_eq!(publish(&client, make_zip_bytes(&make_erm_with_parents("parents-app", "1.1", &[("parents-core", "^1.0")]), vec![])).await, Status::Ok);
+        assert_eq!(publish(&client, make_zip_bytes(&make_erm_with_parents("parents-app", "1.2", &[("parents-core", "[1.0,")]), vec![])).await, Status::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_parent_problems_are_warnings() {
+        // Cycles are found through published ERMs, ones left behind by a previous run would show up early.
+        let _ = fs::remove_dir_all("static/com/example/parents-warn-a");
+        let _ = fs::remove_dir_all("static/com/example/parents-warn-b");
+
+        let client = make_client_with(ServerConfig {
+            parent_policy: ParentPolicy::Warn,
+            ..Default::default()
+        }).await;
+
+        let put = |erm: ExtensionRuntimeModel| client.put(uri!(super::put_object(Some(true))))
+            .header(Header::new("Authorization", "Bearer admin"))
+            .body(make_zip_bytes(&erm, vec![]))
+            .dispatch();
+
+        let r = put(make_erm_with_parents("parents-warn-a", "1.0", &[("parents-warn-b", "1.0")])).await;
+        assert_eq!(r.status(), Status::Ok);
+        let response: PublishResponse = r.into_json().await.unwrap();
+        assert_eq!(response.warnings, vec!["Parent 'com.example:parents-warn-b:1.0' has not been published."]);
+
+        let r = put(make_erm_with_parents("parents-warn-b", "1.0", &[("parents-warn-a", "1.0")])).await;
+        assert_eq!(r.status(), Status::Ok);
+        let response: PublishResponse = r.into_json().await.unwrap();
+        assert_eq!(response.warnings, vec!["The parents close the cycle com.example:parents-warn-b:1.0 -> com.example:parents-warn-a:1.0 -> com.example:parents-warn-b:1.0."]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_publishes_of_a_version() {
+        let _ = fs::remove_dir_all("static/com/example/concurrent");
+        let client = make_client().await;
+
+        let put = || client.put(uri!(super::put_object(_)))
+            .header(Header::new("Authorization", "Bearer nothing"))
+            .body(make_zip_bytes(&make_erm("concurrent", "1.0"), vec![]))
+            .dispatch();
+
+        let (a, b) = tokio::join!(put(), put());
+        let mut statuses = vec![a.status(), b.status()];
+        statuses.sort_by_key(|it| it.code);
+
+        assert_eq!(statuses, vec![Status::Ok, Status::Conflict]);
+    }
+}
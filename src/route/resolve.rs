@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use rocket::{get, Route, routes, State};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+
+use crate::metadata::MetadataHandler;
+use crate::resolve::{resolve, Resolution};
+use crate::responses::HttpResult;
+use crate::types::ExtensionDescriptor;
+
+pub struct ExtensionResolveServer;
+
+impl From<ExtensionResolveServer> for Vec<Route> {
+    fn from(_: ExtensionResolveServer) -> Self {
+        routes![
+            resolve_extension
+        ]
+    }
+}
+
+// An incomplete resolution is still returned, with 422, so clients can show what is wrong with it.
+#[get("/resolve/<descriptor>")]
+fn resolve_extension(
+    descriptor: &str,
+    metadata_handler: &State<MetadataHandler>,
+) -> HttpResult<(Status, Json<Resolution>)> {
+    let descriptor = ExtensionDescriptor::parse_descriptor(descriptor)?;
+    let resolution = resolve(Path::new("static"), &descriptor, metadata_handler)?;
+
+    let status = if resolution.errors.is_empty() {
+        Status::Ok
+    } else {
+        Status::UnprocessableEntity
+    };
+
+    Ok((status, Json(resolution)))
+}
@@ -1,15 +1,15 @@
+use crate::metadata::MetadataHandler;
 use crate::responses::HttpResult;
-use crate::route::metadata::ExtensionMetadataServer;
 use crate::search::ExtensionSearchHandler;
 use crate::types::SearchResponse;
 use rocket::serde::json::Json;
-use rocket::{get, routes, Route};
-use std::cmp::{max, min};
+use rocket::{get, routes, Route, State};
+use std::cmp::min;
 
 pub struct ExtensionSearchServer;
 
-impl Into<Vec<Route>> for ExtensionSearchServer {
-    fn into(self) -> Vec<Route> {
+impl From<ExtensionSearchServer> for Vec<Route> {
+    fn from(_: ExtensionSearchServer) -> Self {
         routes![search,]
     }
 }
@@ -21,10 +21,15 @@ fn search(
     page: usize,
     pagination: usize,
     search_handler: &ExtensionSearchHandler,
+    metadata_handler: &State<MetadataHandler>,
 ) -> HttpResult<Json<SearchResponse>> {
     let handler = search_handler.lock().unwrap();
 
-    let result = handler.search(query.as_str())?;
+    // Extensions whose every version is yanked are hidden from search.
+    let result: Vec<_> = handler.search(query.as_str())?
+        .into_iter()
+        .filter(|it| metadata_handler.is_available(it))
+        .collect();
 
     let result = if page * pagination < result.len() {
         let range = min(result.len(), (page + 1) * pagination);
@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+
+use ed25519_dalek::SigningKey;
+use rocket::{async_trait, Build, Request, Rocket};
+
+use crate::auth::{Authorizer, Principal, Scope};
+use crate::metadata::MetadataHandler;
+use crate::ownership::OwnershipStore;
+use crate::publish::PublishLocks;
+use crate::search::search::SearchHandler;
+use crate::signature::RepositorySigner;
+use crate::types::{ExtensionIdentifier, RepositoryMetadata};
+
+// Trusts every token: "admin" may do anything, "nothing" may publish and yank anywhere, and any other token is a
+// principal named after itself that may publish and yank in the group of the same name.
+pub struct TestAuthorizer;
+
+#[async_trait]
+impl Authorizer for TestAuthorizer {
+    async fn authorize(&self, _request: &Request<'_>, token: &str) -> Option<Principal> {
+        Some(match token {
+            "admin" => Principal {
+                id: token.into(),
+                groups: vec!["*".into()],
+                scopes: vec![Scope::Admin],
+            },
+            "nothing" => Principal {
+                id: token.into(),
+                groups: vec!["*".into()],
+                scopes: vec![Scope::Publish, Scope::Yank],
+            },
+            group => Principal {
+                id: token.into(),
+                groups: vec![group.into()],
+                scopes: vec![Scope::Publish, Scope::Yank],
+            },
+        })
+    }
+}
+
+pub fn test_signer() -> RepositorySigner {
+    RepositorySigner::new(SigningKey::from_bytes(&[1u8; 32]))
+}
+
+pub fn test_repository_metadata() -> RepositoryMetadata {
+    RepositoryMetadata {
+        name: "A test repository".to_string(),
+        description: "A cool description".to_string(),
+        icon: Default::default(),
+        extension_count: 0,
+        app_ids: vec![],
+        public_key: None,
+    }
+}
+
+// A rocket managing everything the routes need but the server config. Metadata, owners and the search index start
+// empty in a directory of their own, which is managed too so it is only removed with the rocket.
+pub fn test_rocket() -> Rocket<Build> {
+    let directory = tempfile::tempdir().unwrap();
+
+    rocket::build()
+        .manage(Arc::new(TestAuthorizer) as Arc<dyn Authorizer>)
+        .manage(test_signer())
+        .manage(test_repository_metadata())
+        .manage(PublishLocks::default())
+        .manage(MetadataHandler::hydrate_cache(directory.path().join("metadata.json")).unwrap())
+        .manage(OwnershipStore::load(directory.path().join("owners.json")).unwrap())
+        .manage(Arc::new(Mutex::new(SearchHandler::<ExtensionIdentifier>::hydrate_cache(directory.path().join("search_index.json")).unwrap())))
+        .manage(directory)
+}
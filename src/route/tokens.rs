@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use rocket::{delete, get, post, Route, routes, State};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+
+use crate::auth::{Authorization, Scope};
+use crate::responses::{HandlerError, HttpResult};
+use crate::token::TokenStore;
+use crate::types::{CreateTokenRequest, CreatedToken, TokenInfo};
+
+pub struct TokenServer;
+
+impl From<TokenServer> for Vec<Route> {
+    fn from(_: TokenServer) -> Self {
+        routes![
+            create_token,
+            list_tokens,
+            describe_token,
+            revoke_token
+        ]
+    }
+}
+
+fn token_not_found(id: &str) -> HandlerError {
+    HandlerError::new(
+        "Token not found".into(),
+        Some(format!("There is no token with the id '{}'.", id)),
+        Status::NotFound,
+    )
+}
+
+// Tokens are managed by admins of every group, `*` is only covered by a principal for every group.
+#[post("/tokens", format = "json", data = "<request>")]
+fn create_token(
+    request: Json<CreateTokenRequest>,
+    authorized: Authorization,
+    token_store: &State<Arc<TokenStore>>,
+) -> HttpResult<(Status, Json<CreatedToken>)> {
+    authorized.require(Scope::Admin, "*")?;
+
+    Ok((Status::Created, Json(token_store.create(request.into_inner())?)))
+}
+
+#[get("/tokens")]
+fn list_tokens(
+    authorized: Authorization,
+    token_store: &State<Arc<TokenStore>>,
+) -> HttpResult<Json<Vec<TokenInfo>>> {
+    authorized.require(Scope::Admin, "*")?;
+
+    Ok(Json(token_store.list()))
+}
+
+#[get("/tokens/<id>")]
+fn describe_token(
+    id: &str,
+    authorized: Authorization,
+    token_store: &State<Arc<TokenStore>>,
+) -> HttpResult<Json<TokenInfo>> {
+    authorized.require(Scope::Admin, "*")?;
+
+    token_store.describe(id)
+        .map(Json)
+        .ok_or_else(|| token_not_found(id))
+}
+
+#[delete("/tokens/<id>")]
+fn revoke_token(
+    id: &str,
+    authorized: Authorization,
+    token_store: &State<Arc<TokenStore>>,
+) -> HttpResult<()> {
+    authorized.require(Scope::Admin, "*")?;
+
+    if !token_store.revoke(id)? {
+        return Err(token_not_found(id));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rocket::http::{ContentType, Header, Status};
+    use rocket::local::asynchronous::Client;
+
+    use crate::auth::{Authorizer, Principal, Scope};
+    use crate::route::tokens::TokenServer;
+    use crate::token::TokenStore;
+    use crate::types::{CreatedToken, TokenInfo};
+
+    #[tokio::test]
+    async fn test_manage_tokens() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut token_store = TokenStore::load(directory.path().join("tokens.json")).unwrap();
+        token_store.insert_environment("admin".into(), Principal {
+            id: "admin".into(),
+            groups: vec!["*".into()],
+            scopes: vec![Scope::Admin],
+        });
+        let token_store = Arc::new(token_store);
+
+        let client = Client::tracked(
+            rocket::build()
+                .mount("/", TokenServer)
+                .manage(token_store.clone() as Arc<dyn Authorizer>)
+                .manage(token_store)
+        ).await.unwrap();
+
+        let r = client.post("/tokens")
+            .header(Header::new("Authorization", "Bearer admin"))
+            .header(ContentType::JSON)
+            .body(r#"{ "label": "ci", "groups": ["com.example"], "scopes": ["publish"] }"#)
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Created);
+        let created: CreatedToken = r.into_json().await.unwrap();
+
+        // The new token is valid right away, but may not manage tokens itself.
+        let r = client.get("/tokens")
+            .header(Header::new("Authorization", format!("Bearer {}", created.token)))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Forbidden);
+
+        let r = client.get(format!("/tokens/{}", created.info.id))
+            .header(Header::new("Authorization", "Bearer admin"))
+            .dispatch().await;
+        let info: TokenInfo = r.into_json().await.unwrap();
+        assert_eq!(info.label, "ci");
+        assert!(info.last_used.is_some());
+
+        let r = client.delete(format!("/tokens/{}", created.info.id))
+            .header(Header::new("Authorization", "Bearer admin"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::Ok);
+
+        let r = client.get("/tokens")
+            .header(Header::new("Authorization", "Bearer admin"))
+            .dispatch().await;
+        let tokens: Vec<TokenInfo> = r.into_json().await.unwrap();
+        assert!(tokens.is_empty());
+
+        let r = client.delete(format!("/tokens/{}", created.info.id))
+            .header(Header::new("Authorization", "Bearer admin"))
+            .dispatch().await;
+        assert_eq!(r.status(), Status::NotFound);
+    }
+}
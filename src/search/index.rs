@@ -1,11 +1,7 @@
 use std::collections::HashMap;
-use rocket::futures::SinkExt;
-use rocket::http::hyper::body::HttpBody;
 use rocket::serde::Serialize;
 use serde::Deserialize;
 
-use crate::types::ExtensionIdentifier;
-
 // pub trait Index<T: Sized> {
 //     fn find(
 //         &self,
@@ -47,12 +43,12 @@ impl<T> IndexNode<T> {
         &self,
         token: &str,
     ) -> Vec<&(T, u8)> {
-        if token == "" {
+        if token.is_empty() {
             let mut this_rank: Vec<&(T, u8)> = self.rank.iter().collect();
 
             self.children
-                .iter()
-                .flat_map(|(c, child)| {
+                .values()
+                .flat_map(|child| {
                     child.find("")
                 })
                 .for_each(|val| {
@@ -68,7 +64,7 @@ impl<T> IndexNode<T> {
             .get(&first_char)
             .map(|t| {
                 t.find(&token[1..])
-            }).unwrap_or_else(Vec::new)
+            }).unwrap_or_default()
     }
 
     pub fn insert(
@@ -80,7 +76,7 @@ impl<T> IndexNode<T> {
     where
         T: PartialEq,
     {
-        if token == "" {
+        if token.is_empty() {
             if !self.rank.iter().any(|it| {
                 it.0 == value
             }) {
@@ -90,7 +86,7 @@ impl<T> IndexNode<T> {
             let first_char = token.chars().next().unwrap();
 
             if !self.children.contains_key(&first_char) {
-                self.children.insert(first_char.clone(), Box::new(IndexNode {
+                self.children.insert(first_char, Box::new(IndexNode {
                     path: format!("{}{}", self.path, first_char),
                     rank: vec![],
                     children: Default::default(),
@@ -102,6 +98,43 @@ impl<T> IndexNode<T> {
             }
         }
     }
+
+    // Every token the value is indexed under, with the rank it has there.
+    pub fn entries(
+        &self,
+        value: &T,
+    ) -> Vec<(String, u8)>
+    where
+        T: PartialEq,
+    {
+        let mut entries: Vec<(String, u8)> = self.rank.iter()
+            .filter(|it| it.0 == *value)
+            .map(|it| (self.path.clone(), it.1))
+            .collect();
+
+        for child in self.children.values() {
+            entries.extend(child.entries(value));
+        }
+
+        entries
+    }
+
+    // Removes the value from every token, pruning nodes that no longer lead to anything.
+    pub fn remove(
+        &mut self,
+        value: &T,
+    )
+    where
+        T: PartialEq,
+    {
+        self.rank.retain(|it| it.0 != *value);
+
+        self.children.retain(|_, child| {
+            child.remove(value);
+
+            !child.rank.is_empty() || !child.children.is_empty()
+        });
+    }
 }
 
 #[cfg(test)]
@@ -130,4 +163,32 @@ mod tests {
 
         println!("{:?}", node.find("test-inga"));
     }
+
+    #[test]
+    fn test_tri_remove() {
+        let mut node: IndexNode<String> = IndexNode::new();
+
+        node.insert("test", "first".to_string(), 1);
+        node.insert("test", "second".to_string(), 1);
+        node.insert("testing", "first".to_string(), 1);
+
+        node.remove(&"first".to_string());
+
+        assert_eq!(node.find("test"), vec![&("second".to_string(), 1)]);
+        assert!(node.find("testing").is_empty());
+        assert!(!node.children[&'t'].children[&'e'].children[&'s'].children[&'t'].children.contains_key(&'i'));
+    }
+
+    #[test]
+    fn test_tri_entries() {
+        let mut node: IndexNode<String> = IndexNode::new();
+
+        node.insert("test", "first".to_string(), 1);
+        node.insert("testing", "first".to_string(), 10);
+        node.insert("test", "second".to_string(), 1);
+
+        let mut entries = node.entries(&"first".to_string());
+        entries.sort();
+        assert_eq!(entries, vec![("test".to_string(), 1), ("testing".to_string(), 10)]);
+    }
 }
\ No newline at end of file
@@ -8,6 +8,7 @@ use crate::types::ExtensionIdentifier;
 
 mod index;
 mod token;
+#[allow(clippy::module_inception)]
 pub mod search;
 
 #[derive(Debug)]
@@ -1,15 +1,11 @@
 use std::collections::HashMap;
-use std::error::Error;
-use std::fs::{create_dir_all, File};
+use std::fs::File;
 use std::hash::Hash;
 use std::io;
-use std::io::Write;
 use std::marker::PhantomData;
-use std::ops::Deref;
 use std::path::{Path, PathBuf};
 
-use serde::{Deserialize, Serialize};
-
+use crate::journal::write_atomically;
 use crate::search::index::IndexNode;
 use crate::search::SearchError;
 use crate::search::token::WordTokenizer;
@@ -18,6 +14,8 @@ use crate::types::ExtensionIdentifier;
 pub struct SearchHandler<T: Sized> {
     index_node: IndexNode<T>,
     tokenizer: WordTokenizer,
+    // Where the index is persisted, None for an index that only lives in memory.
+    path: Option<PathBuf>,
     phantom_data: PhantomData<T>,
 }
 
@@ -25,13 +23,14 @@ impl<T> SearchHandler<T>
 where
     T: Sized + Clone + Eq + Hash,
 {
-
+    #[allow(dead_code)]
     pub fn new() -> Result<SearchHandler<T>, SearchError> {
         Ok(SearchHandler {
             index_node: IndexNode::new(),
             tokenizer: WordTokenizer::new().map_err(|e| {
                 SearchError::TokenizationError(e)
             })?,
+            path: None,
             phantom_data: Default::default(),
         })
     }
@@ -74,49 +73,81 @@ where
         Ok(result.iter().map(|t| t.1.clone()).collect())
     }
 
+    #[allow(dead_code)]
     pub fn index(
         &mut self,
         content: &str,
         value: T,
         rank: u8,
     ) -> Result<(), SearchError> {
-        let tokens = self.tokenizer.tokenize(content).map_err(|e| {
-            SearchError::TokenizationError(e)
-        })?;
+        self.index_all(&[(content, rank)], value)
+    }
 
-        for token in tokens {
-            self.index_node.insert(
-                token.as_str(),
-                value.clone(),
-                rank,
-            );
+    // Tokenizes all the content before inserting any of it, so a failure leaves the index untouched.
+    pub fn index_all(
+        &mut self,
+        contents: &[(&str, u8)],
+        value: T,
+    ) -> Result<(), SearchError> {
+        let tokens = contents.iter()
+            .map(|(content, rank)| self.tokenizer.tokenize(content).map(|tokens| (tokens, *rank)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(SearchError::TokenizationError)?;
+
+        for (tokens, rank) in tokens {
+            for token in tokens {
+                self.index_node.insert(
+                    token.as_str(),
+                    value.clone(),
+                    rank,
+                );
+            }
         }
 
         Ok(())
     }
-}
 
-impl SearchHandler<ExtensionIdentifier> {
-    pub fn persist_to<P: Into<PathBuf>>(&self, path: P) -> io::Result<()> {
-        let path = path.into();
-        if !Path::new(&path).exists() {
-            if let Some(x) = path.parent() {
-                create_dir_all(x.clone())?;
-            }
-            File::create(path.clone())?;
-        };
+    pub fn remove(
+        &mut self,
+        value: &T,
+    ) {
+        self.index_node.remove(value);
+    }
 
-        let mut file = std::fs::OpenOptions::new().write(true).truncate(true).open(path)?;
+    // What the value is indexed under, so it can be put back as it was with `restore`.
+    pub fn entries(
+        &self,
+        value: &T,
+    ) -> Vec<(String, u8)> {
+        self.index_node.entries(value)
+    }
 
-        let content = serde_json::to_vec(&self.index_node).unwrap();
+    // Replaces everything the value is indexed under with entries taken before, no entries leave it unindexed.
+    pub fn restore(
+        &mut self,
+        value: &T,
+        entries: Vec<(String, u8)>,
+    ) {
+        self.index_node.remove(value);
+        for (token, rank) in entries {
+            self.index_node.insert(&token, value.clone(), rank);
+        }
+    }
+}
 
-        file.write_all(content.deref())
+impl SearchHandler<ExtensionIdentifier> {
+    // Replaces the persisted index as a whole, a crash leaves either the old or the new index.
+    pub fn persist(&self) -> io::Result<()> {
+        match &self.path {
+            Some(path) => write_atomically(path, &serde_json::to_vec(&self.index_node)?),
+            None => Ok(()),
+        }
     }
 
     pub fn hydrate_cache< P: Into<PathBuf>>(path: P) -> Result<SearchHandler<ExtensionIdentifier>, SearchError> {
         let path = path.into();
         let index: IndexNode<ExtensionIdentifier> = if Path::new(&path).exists() {
-            let file = File::open(path).map_err(|e| {
+            let file = File::open(&path).map_err(|e| {
                 SearchError::IoError(e)
             })?;
 
@@ -130,6 +161,7 @@ impl SearchHandler<ExtensionIdentifier> {
             tokenizer: WordTokenizer::new().map_err(|it| {
                 SearchError::TokenizationError(it)
             })?,
+            path: Some(path),
             phantom_data: Default::default(),
         })
     }
@@ -146,6 +178,7 @@ mod tests {
         let mut handler = SearchHandler {
             index_node: IndexNode::new(),
             tokenizer: WordTokenizer::new().unwrap(),
+            path: None,
             phantom_data: Default::default(),
         };
 
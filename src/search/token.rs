@@ -38,11 +38,10 @@ impl WordTokenizer {
 
         let tokens: Vec<String> = tokens.iter()
             .filter(|&token| !stopwords.contains(token.as_str()))
-            .map(|token| stemmer.stem(token).to_string())  // Stem the words
-            .map(|t| t.clone())
+            .map(|token| stemmer.stem(token).to_string())
             .collect();
 
-        if tokens.len() == 0 {
+        if tokens.is_empty() {
             return Ok(vec!["".to_string()])
         }
 
@@ -52,7 +51,7 @@ impl WordTokenizer {
     fn combine_tokens(&self, tokens: &[String]) -> Vec<String> {
         let mut vec: Vec<String> = Vec::new();
         for x in tokens {
-            if let Some(x) = x.strip_prefix(&self.1) {
+            if let Some(x) = x.strip_prefix(self.1) {
                 let last = vec.pop().unwrap_or("".to_string());
                 vec.push(format!("{}{}", last, x))
             } else {
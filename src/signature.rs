@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, File};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+
+// Bundle entry holding the publisher's hex encoded ed25519 signature over the bundle manifest.
+pub const SIGNATURE_ENTRY: &str = "bundle.sig";
+// Written by the repository next to the signature so clients can verify it themselves.
+pub const MANIFEST_ENTRY: &str = "bundle.manifest";
+// Extension of the repository's signature written next to every published file.
+pub const REPOSITORY_SIGNATURE_EXTENSION: &str = "sig";
+
+// The signed message, one `<sha256>  <entry>` line for every bundle entry except the signature, sorted by entry name.
+pub fn manifest(digests: &BTreeMap<String, String>) -> String {
+    digests.iter()
+        .map(|(name, digest)| format!("{}  {}\n", digest, name))
+        .collect()
+}
+
+pub fn parse_key(key: &str) -> Option<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(key.trim()).ok()?.try_into().ok()?;
+
+    VerifyingKey::from_bytes(&bytes).ok()
+}
+
+pub fn parse_signature(signature: &str) -> Option<Signature> {
+    let bytes: [u8; 64] = hex::decode(signature.trim()).ok()?.try_into().ok()?;
+
+    Some(Signature::from_bytes(&bytes))
+}
+
+pub fn verify(keys: &[VerifyingKey], message: &[u8], signature: &Signature) -> bool {
+    keys.iter().any(|key| key.verify_strict(message, signature).is_ok())
+}
+
+// The repository's own key, used to sign every published file and the managed metadata it serves.
+#[derive(Clone)]
+pub struct RepositorySigner {
+    key: SigningKey,
+}
+
+impl RepositorySigner {
+    pub fn new(key: SigningKey) -> RepositorySigner {
+        RepositorySigner { key }
+    }
+
+    // The key is stored as a hex encoded ed25519 seed, a new one is generated when the file does not exist.
+    pub fn load_or_create<T: Into<PathBuf>>(path: T) -> Result<RepositorySigner, io::Error> {
+        let path = path.into();
+        if Path::new(&path).exists() {
+            let seed: [u8; 32] = hex::decode(std::fs::read_to_string(&path)?.trim()).ok()
+                .and_then(|it| it.try_into().ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid repository key"))?;
+
+            return Ok(RepositorySigner::new(SigningKey::from_bytes(&seed)));
+        }
+
+        if let Some(x) = path.parent() {
+            create_dir_all(x)?;
+        }
+
+        let key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+        let mut file = File::create(&path)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        }
+        file.write_all(hex::encode(key.to_bytes()).as_bytes())?;
+
+        Ok(RepositorySigner::new(key))
+    }
+
+    pub fn public_key(&self) -> String {
+        hex::encode(self.key.verifying_key().to_bytes())
+    }
+
+    // Hex encoded ed25519 signature of the message.
+    pub fn sign(&self, message: &[u8]) -> String {
+        hex::encode(self.key.sign(message).to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use crate::signature::{manifest, parse_key, parse_signature, verify, RepositorySigner};
+
+    #[test]
+    fn test_verify_manifest() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let other_key = SigningKey::from_bytes(&[8u8; 32]);
+
+        let manifest = manifest(&BTreeMap::from([
+            ("test1.jar".to_string(), "bb".to_string()),
+            ("erm.json".to_string(), "aa".to_string()),
+        ]));
+        assert_eq!(manifest, "aa  erm.json\nbb  test1.jar\n");
+
+        let signature = hex::encode(signing_key.sign(manifest.as_bytes()).to_bytes());
+        let signature = parse_signature(&signature).unwrap();
+
+        let key = parse_key(&hex::encode(signing_key.verifying_key().to_bytes())).unwrap();
+        let other_key = parse_key(&hex::encode(other_key.verifying_key().to_bytes())).unwrap();
+
+        assert!(verify(&[other_key, key], manifest.as_bytes(), &signature));
+        assert!(!verify(&[other_key], manifest.as_bytes(), &signature));
+        assert!(!verify(&[key], b"tampered", &signature));
+        assert!(parse_signature("not hex").is_none());
+    }
+
+    #[test]
+    fn test_repository_signer_persists_key() {
+        let _ = std::fs::remove_file("config/test_repository.key");
+
+        let signer = RepositorySigner::load_or_create("config/test_repository.key").unwrap();
+        let loaded = RepositorySigner::load_or_create("config/test_repository.key").unwrap();
+        assert_eq!(signer.public_key(), loaded.public_key());
+
+        let signature = parse_signature(&signer.sign(b"message")).unwrap();
+        assert!(verify(&[parse_key(&loaded.public_key()).unwrap()], b"message", &signature));
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chrono::Utc;
+
+use crate::types::VersionType;
+
+const SNAPSHOT_SUFFIX: &str = "-SNAPSHOT";
+
+// Snapshot versions can be published any number of times, every upload is stored as a timestamped build.
+// Whatever `VersionType::classify` calls a snapshot ends in `-SNAPSHOT`, a snapshot also needs a version before it.
+pub fn is_snapshot(version: &str) -> bool {
+    version.len() > SNAPSHOT_SUFFIX.len() && VersionType::classify(version).is_ok_and(|it| it == VersionType::Snapshot)
+}
+
+// The current UTC time in the `yyyyMMdd.HHmmss` format maven uses for snapshot builds.
+pub fn timestamp() -> String {
+    Utc::now().format("%Y%m%d.%H%M%S").to_string()
+}
+
+// `1.3.0-SNAPSHOT` published at 2026-10-17 12:00:00 as the 7th build becomes `1.3.0-20261017.120000-7`.
+pub fn build_version(version: &str, timestamp: &str, build_number: u32) -> String {
+    format!(
+        "{}-{}-{}",
+        &version[..version.len() - SNAPSHOT_SUFFIX.len()],
+        timestamp,
+        build_number
+    )
+}
+
+// Removes every file published for a single build from the snapshot's version directory.
+pub fn remove_build_files(path: &Path, name: &str, build_version: &str) -> io::Result<()> {
+    let prefix = format!("{}-{}", name, build_version);
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some(rest) = file_name.to_str().and_then(|it| it.strip_prefix(&prefix)) else {
+            continue;
+        };
+
+        // Build 7 must not take the files of build 70 with it.
+        if rest.starts_with(['-', '.']) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::snapshot::{build_version, is_snapshot, remove_build_files};
+
+    #[test]
+    fn test_build_version() {
+        assert!(is_snapshot("1.3.0-SNAPSHOT"));
+        assert!(is_snapshot("1.3.0-snapshot"));
+        assert!(!is_snapshot("1.3.0"));
+        assert!(!is_snapshot("-SNAPSHOT"));
+        // Numbered snapshot qualifiers are not snapshots, nor any other valid version.
+        assert!(!is_snapshot("1.3.0-SNAPSHOT.2"));
+        assert!(!is_snapshot("1.3.0-snapshot3"));
+
+        assert_eq!(build_version("1.3.0-SNAPSHOT", "20261017.120000", 7), "1.3.0-20261017.120000-7");
+    }
+
+    #[test]
+    fn test_remove_build_files() {
+        let path = tempfile::tempdir().unwrap();
+        for file in ["ext-1.0-20261017.120000-7-erm.json", "ext-1.0-20261017.120000-7-erm.json.sha256", "ext-1.0-20261017.120000-70-erm.json"] {
+            fs::write(path.path().join(file), "").unwrap();
+        }
+
+        remove_build_files(path.path(), "ext", "1.0-20261017.120000-7").unwrap();
+
+        let remaining: Vec<String> = fs::read_dir(path.path()).unwrap()
+            .map(|it| it.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert_eq!(remaining, vec!["ext-1.0-20261017.120000-70-erm.json"]);
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::mem;
+use std::sync::{Mutex, RwLock};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rocket::http::Status;
+use rocket::{async_trait, Request};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::auth::{Authorizer, Principal};
+use crate::journal::write_atomically;
+use crate::responses::{HandlerError, HttpResult};
+use crate::types::{CreateTokenRequest, CreatedToken, TokenInfo};
+
+// Tokens managed through the /tokens routes, persisted in data/tokens.json. Only a salted hash of each secret is
+// stored, the token `<id>.<secret>` is shown once when it is created.
+pub struct TokenStore {
+    path: PathBuf,
+    tokens: RwLock<Vec<StoredToken>>,
+    // When each token was last used since the last flush, requests only note it here instead of writing the file.
+    uses: Mutex<HashMap<String, String>>,
+    // Tokens from the environment, compared as they are and never persisted.
+    environment: Vec<(String, Principal)>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct TokenFile {
+    tokens: Vec<StoredToken>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct StoredToken {
+    id: String,
+    label: String,
+    salt: String,
+    hash: String,
+    #[serde(flatten)]
+    principal: Principal,
+    created: String,
+    expires: Option<String>,
+    last_used: Option<String>,
+}
+
+impl StoredToken {
+    fn info(&self) -> TokenInfo {
+        TokenInfo {
+            id: self.id.clone(),
+            label: self.label.clone(),
+            principal: self.principal.id.clone(),
+            groups: self.principal.groups.clone(),
+            scopes: self.principal.scopes.clone(),
+            created: self.created.clone(),
+            expires: self.expires.clone(),
+            last_used: self.last_used.clone(),
+        }
+    }
+
+    fn is_expired(&self, now: &DateTime<Utc>) -> bool {
+        self.expires.as_deref()
+            .and_then(|it| DateTime::parse_from_rfc3339(it).ok())
+            .is_some_and(|it| it <= *now)
+    }
+}
+
+fn hash(salt: &str, secret: &str) -> String {
+    hex::encode(Sha256::new().chain_update(salt).chain_update(secret).finalize())
+}
+
+// Compares every byte, so the time taken does not tell how much of a guess was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl TokenStore {
+    pub fn load<T: Into<PathBuf>>(path: T) -> Result<TokenStore, io::Error> {
+        let path = path.into();
+        let file = if Path::new(&path).exists() {
+            serde_json::from_reader(File::open(&path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        } else {
+            TokenFile::default()
+        };
+
+        Ok(TokenStore {
+            path,
+            tokens: RwLock::new(file.tokens),
+            uses: Mutex::new(HashMap::new()),
+            environment: Vec::new(),
+        })
+    }
+
+    pub fn insert_environment(&mut self, token: String, principal: Principal) {
+        self.environment.push((token, principal));
+    }
+
+    // True when no token at all is known, neither from the environment nor stored.
+    pub fn is_empty(&self) -> bool {
+        self.environment.is_empty() && self.tokens.read().unwrap().is_empty()
+    }
+
+    fn persist(&self, tokens: &[StoredToken]) -> io::Result<()> {
+        write_atomically(&self.path, &serde_json::to_vec(&TokenFile {
+            tokens: tokens.to_vec(),
+        })?)
+    }
+
+    pub fn create(&self, request: CreateTokenRequest) -> HttpResult<CreatedToken> {
+        if let Some(expires) = &request.expires {
+            if DateTime::parse_from_rfc3339(expires).is_err() {
+                return Err(HandlerError::new(
+                    "Invalid token expiry".into(),
+                    Some(format!("'{}' is not an RFC 3339 timestamp.", expires)),
+                    Status::BadRequest,
+                ));
+            }
+        }
+
+        let id = hex::encode(rand::random::<[u8; 8]>());
+        let secret = hex::encode(rand::random::<[u8; 32]>());
+        let salt = hex::encode(rand::random::<[u8; 16]>());
+
+        let principal_id = request.principal.unwrap_or_else(|| request.label.clone());
+        let token = StoredToken {
+            id: id.clone(),
+            label: request.label,
+            hash: hash(&salt, &secret),
+            salt,
+            principal: Principal {
+                id: principal_id,
+                groups: request.groups,
+                scopes: request.scopes,
+            },
+            created: format_time(Utc::now()),
+            expires: request.expires,
+            last_used: None,
+        };
+
+        let mut tokens = self.tokens.write().unwrap();
+        tokens.push(token.clone());
+        if let Err(e) = self.persist(&tokens) {
+            tokens.pop();
+            return Err(e.into());
+        }
+
+        Ok(CreatedToken {
+            token: format!("{}.{}", id, secret),
+            info: token.info(),
+        })
+    }
+
+    // Uses that are not flushed yet are reported too.
+    fn info(&self, token: &StoredToken) -> TokenInfo {
+        let mut info = token.info();
+        if let Some(last_used) = self.uses.lock().unwrap().get(&token.id) {
+            info.last_used = Some(last_used.clone());
+        }
+        info
+    }
+
+    pub fn list(&self) -> Vec<TokenInfo> {
+        self.tokens.read().unwrap().iter().map(|it| self.info(it)).collect()
+    }
+
+    pub fn describe(&self, id: &str) -> Option<TokenInfo> {
+        self.tokens.read().unwrap().iter().find(|it| it.id == id).map(|it| self.info(it))
+    }
+
+    // Returns false when there is no such token.
+    pub fn revoke(&self, id: &str) -> HttpResult<bool> {
+        let mut tokens = self.tokens.write().unwrap();
+        let Some(index) = tokens.iter().position(|it| it.id == id) else {
+            return Ok(false);
+        };
+
+        let revoked = tokens.remove(index);
+        if let Err(e) = self.persist(&tokens) {
+            tokens.insert(index, revoked);
+            return Err(e.into());
+        }
+
+        Ok(true)
+    }
+
+    // Writes the uses noted since the last flush to the file. Uses that could not be written are kept for the next
+    // flush, unless the token was used again in the meantime.
+    pub fn flush_uses(&self) -> io::Result<()> {
+        let uses = mem::take(&mut *self.uses.lock().unwrap());
+        if uses.is_empty() {
+            return Ok(());
+        }
+
+        let mut tokens = self.tokens.write().unwrap();
+        let previous = tokens.clone();
+        for token in tokens.iter_mut() {
+            if let Some(last_used) = uses.get(&token.id) {
+                token.last_used = Some(last_used.clone());
+            }
+        }
+
+        if let Err(e) = self.persist(&tokens) {
+            *tokens = previous;
+            let mut pending = self.uses.lock().unwrap();
+            for (id, last_used) in uses {
+                pending.entry(id).or_insert(last_used);
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Authorizer for TokenStore {
+    async fn authorize(&self, _request: &Request<'_>, token: &str) -> Option<Principal> {
+        if let Some((_, principal)) = self.environment.iter().find(|(it, _)| constant_time_eq(it.as_bytes(), token.as_bytes())) {
+            return Some(principal.clone());
+        }
+
+        let (id, secret) = token.split_once('.')?;
+        let now = Utc::now();
+
+        let principal = {
+            let tokens = self.tokens.read().unwrap();
+            let stored = tokens.iter().find(|it| it.id == id)?;
+            if !constant_time_eq(hash(&stored.salt, secret).as_bytes(), stored.hash.as_bytes()) || stored.is_expired(&now) {
+                return None;
+            }
+            stored.principal.clone()
+        };
+
+        self.uses.lock().unwrap().insert(id.to_string(), format_time(now));
+
+        Some(principal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::local::asynchronous::Client;
+
+    use crate::auth::{Authorizer, Scope};
+    use crate::token::TokenStore;
+    use crate::types::CreateTokenRequest;
+
+    #[tokio::test]
+    async fn test_token_store() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("tokens.json");
+        let client = Client::untracked(rocket::build()).await.unwrap();
+        let request = client.get("/");
+        let request = request.inner();
+
+        let store = TokenStore::load(&path).unwrap();
+        let created = store.create(CreateTokenRequest {
+            label: "ci".into(),
+            principal: None,
+            groups: vec!["com.example".into()],
+            scopes: vec![Scope::Publish],
+            expires: None,
+        }).unwrap();
+        let expired = store.create(CreateTokenRequest {
+            label: "old".into(),
+            principal: None,
+            groups: vec!["*".into()],
+            scopes: vec![Scope::Admin],
+            expires: Some("2020-01-01T00:00:00Z".into()),
+        }).unwrap();
+
+        let principal = store.authorize(request, &created.token).await.unwrap();
+        assert_eq!(principal.id, "ci");
+        assert_eq!(principal.groups, vec!["com.example".to_string()]);
+        assert!(store.authorize(request, &format!("{}.wrong", created.info.id)).await.is_none());
+        assert!(store.authorize(request, &expired.token).await.is_none());
+        assert!(store.describe(&created.info.id).unwrap().last_used.is_some());
+
+        // Uses are only written down when they are flushed.
+        assert!(TokenStore::load(&path).unwrap().describe(&created.info.id).unwrap().last_used.is_none());
+        store.flush_uses().unwrap();
+
+        // Only the hash of a secret is written down, and what is written is read back.
+        let (_, secret) = created.token.split_once('.').unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains(secret));
+        let store = TokenStore::load(&path).unwrap();
+        assert!(store.describe(&created.info.id).unwrap().last_used.is_some());
+        assert!(store.authorize(request, &created.token).await.is_some());
+        assert_eq!(store.list().len(), 2);
+
+        assert!(store.revoke(&created.info.id).unwrap());
+        assert!(!store.revoke(&created.info.id).unwrap());
+        assert!(store.authorize(request, &created.token).await.is_none());
+    }
+}
@@ -1,24 +1,32 @@
 use std::collections::HashMap;
-use std::fmt::format;
-use std::io::Read;
+use std::io::{Read, Seek};
+use std::path::PathBuf;
 
 use rocket::http::Status;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
+use zip::ZipArchive;
 
+use crate::auth::Scope;
 use crate::responses::{HandlerError, HttpResult};
 use crate::types::VersionType::Release;
 
-pub struct ExtensionBundle<T: Read> {
+pub struct ExtensionBundle<R: Read + Seek> {
     pub runtime_model: ExtensionRuntimeModel,
     pub metadata: ExtensionMetadata,
-    pub files: Vec<(T, String)>
+    pub archive: ZipArchive<R>,
+    // Names of the archive entries that will be published.
+    pub files: Vec<String>,
+    // The version published file names carry, a snapshot is published under its timestamped build version.
+    pub file_version: String,
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug)]
 pub enum VersionType {
     Release,
+    Alpha,
     Beta,
     ReleaseCandidate,
+    Snapshot,
 }
 
 impl Serialize for VersionType {
@@ -33,47 +41,105 @@ impl Serialize for VersionType {
 }
 
 impl VersionType {
+    // The channel is the alphabetic start of the suffix, it may be followed by a number
+    // (`-rc2`, `-rc.2`) and further numeric identifiers (`-beta.1.3`). Snapshots are numbered by their builds instead,
+    // `-SNAPSHOT` is never followed by anything.
     pub fn classify<T: Into<String>>(version: T) -> HttpResult<VersionType> {
         let version = version.into();
-        if let Some(pos) = version.find("-") {
-            let suffix = &version[(pos + 1)..];
-            let suffix = suffix.to_lowercase();
-            match suffix.as_str() {
-                "beta" => Ok(VersionType::Beta),
-                "rc" => Ok(VersionType::ReleaseCandidate),
-                _ => Err(HandlerError::new(
-                    "Invalid extension version".into(),
-                    Some("Extension version suffix is invalid, must either end in '', '-BETA', or '-RC".into()),
-                    Status::BadRequest,
-                ))
-            }
-        } else {
-            Ok(Release)
+        let version = version.split('+').next().unwrap();
+        let Some((_, suffix)) = version.split_once('-') else {
+            return Ok(Release);
+        };
+        let suffix = suffix.to_lowercase();
+
+        let channel_end = suffix.find(|it: char| !it.is_ascii_alphabetic()).unwrap_or(suffix.len());
+        let (channel, number) = suffix.split_at(channel_end);
+        let numbered = number.trim_start_matches(['.', '-'])
+            .split(['.', '-'])
+            .all(|it| it.is_empty() || it.chars().all(|it| it.is_ascii_digit()));
+
+        let version_type = match channel {
+            "alpha" => Some(VersionType::Alpha),
+            "beta" => Some(VersionType::Beta),
+            "rc" => Some(VersionType::ReleaseCandidate),
+            "snapshot" => Some(VersionType::Snapshot),
+            _ => None,
+        };
+
+        match version_type {
+            Some(VersionType::Snapshot) if !number.is_empty() => Err(HandlerError::new(
+                "Invalid extension version".into(),
+                Some("Snapshot versions must end in '-SNAPSHOT', their builds are numbered when they are published".into()),
+                Status::BadRequest,
+            )),
+            Some(version_type) if numbered => Ok(version_type),
+            _ => Err(HandlerError::new(
+                "Invalid extension version".into(),
+                Some("Extension version suffix is invalid, must either end in '', '-ALPHA', '-BETA', '-RC' or '-SNAPSHOT', optionally followed by a number like '-RC.2'".into()),
+                Status::BadRequest,
+            ))
+        }
+    }
+
+    // Channels by the name an update check asks for, `release` is the channel without a suffix.
+    pub fn from_channel(channel: &str) -> Option<VersionType> {
+        match channel.to_lowercase().as_str() {
+            "release" => Some(Release),
+            "alpha" => Some(VersionType::Alpha),
+            "beta" => Some(VersionType::Beta),
+            "rc" => Some(VersionType::ReleaseCandidate),
+            "snapshot" => Some(VersionType::Snapshot),
+            _ => None,
+        }
+    }
+
+    // Higher is more stable, a channel also accepts every version that is more stable than it.
+    pub fn stability(&self) -> u8 {
+        match self {
+            VersionType::Snapshot => 0,
+            VersionType::Alpha => 1,
+            VersionType::Beta => 2,
+            VersionType::ReleaseCandidate => 3,
+            Release => 4,
         }
     }
 
     pub fn suffix(&self) -> &'static str {
         match self {
             Release => "",
+            VersionType::Alpha => "alpha",
             VersionType::Beta => "beta",
-            VersionType::ReleaseCandidate => "rc"
+            VersionType::ReleaseCandidate => "rc",
+            VersionType::Snapshot => "snapshot",
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct LatestVersion {
     pub release: Option<String>,
+    #[serde(default)]
+    pub alpha: Option<String>,
     pub beta: Option<String>,
     pub rc: Option<String>,
+    #[serde(default)]
+    pub snapshot: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Checksums {
+    pub sha256: String,
+    pub sha1: String,
+    pub md5: String,
 }
 
-impl Default for LatestVersion {
-    fn default() -> Self {
-        LatestVersion {
-            release: None,
-            beta: None,
-            rc: None,
+impl Checksums {
+    pub fn by_extension(&self, extension: &str) -> Option<&str> {
+        match extension {
+            "sha256" => Some(&self.sha256),
+            "sha1" => Some(&self.sha1),
+            "md5" => Some(&self.md5),
+            _ => None,
         }
     }
 }
@@ -83,6 +149,28 @@ pub struct VersionInfo {
     pub version: String,
     pub release_type: VersionType,
     pub metadata_path: String,
+    // Digests of every published file in this version, keyed by file name.
+    pub checksums: HashMap<String, Checksums>,
+    pub yanked: bool,
+}
+
+// A single upload of a snapshot version.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SnapshotBuild {
+    // The timestamped version, like `1.3.0-20261017.120000-7`.
+    pub version: String,
+    pub timestamp: String,
+    pub build_number: u32,
+    pub checksums: HashMap<String, Checksums>,
+}
+
+#[derive(Serialize)]
+pub struct SnapshotInfo {
+    pub version: String,
+    pub timestamp: String,
+    pub build_number: u32,
+    pub metadata_path: String,
+    pub checksums: HashMap<String, Checksums>,
 }
 
 #[derive(Serialize)]
@@ -90,6 +178,8 @@ pub struct ManagedExtensionMetadata {
     pub downloads: u32,
     pub latest: LatestVersion,
     pub versions: Vec<VersionInfo>,
+    // Builds of every snapshot version, keyed by the snapshot version and ordered oldest first.
+    pub snapshots: HashMap<String, Vec<SnapshotInfo>>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -99,6 +189,9 @@ pub struct RepositoryMetadata {
     pub icon: Option<String>,
     pub extension_count: u32,
     pub app_ids: Vec<String>,
+    // Hex encoded ed25519 key that signs every published file, filled in by the repository.
+    #[serde(default)]
+    pub public_key: Option<String>,
 }
 
 #[derive(Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
@@ -111,6 +204,42 @@ impl ExtensionIdentifier {
     pub fn as_key(&self) -> String {
         format!("{}:{}", self.group, self.name)
     }
+
+    // Where the extension lives under the registry, `com.example:name` becomes `com/example/name`.
+    pub fn path(&self) -> PathBuf {
+        self.group.split(".").fold(PathBuf::new(), |acc, it| {
+            acc.join(it)
+        }).join(&self.name)
+    }
+}
+
+// Groups, names and versions end up in paths and file names under static/, so every group segment, the name and the
+// version are limited to `[A-Za-z0-9._-]` and may not be `.` or `..`. This also refuses build metadata after a `+`.
+pub fn check_coordinates(group: &str, name: &str, version: &str) -> HttpResult<()> {
+    let safe = |it: &str| {
+        !it.is_empty() && it != "." && it != ".." && it.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    };
+
+    let mut violations = Vec::new();
+    if !group.split('.').all(safe) {
+        violations.push(format!("The group '{}' must be segments of [A-Za-z0-9_-] separated by '.'.", group));
+    }
+    if !safe(name) {
+        violations.push(format!("The name '{}' may only contain [A-Za-z0-9._-].", name));
+    }
+    if !safe(version) {
+        violations.push(format!("The version '{}' may only contain [A-Za-z0-9._-].", version));
+    }
+
+    if !violations.is_empty() {
+        return Err(HandlerError::new(
+            "Invalid extension coordinates".into(),
+            Some(violations.join("\n")),
+            Status::BadRequest,
+        ));
+    }
+
+    Ok(())
 }
 
 impl From<&ExtensionRuntimeModel> for ExtensionIdentifier {
@@ -180,12 +309,33 @@ pub struct ExtensionDescriptor {
 }
 
 impl ExtensionDescriptor {
-    pub fn parse_descriptor(descriptor: &str) -> Self {
+    pub fn parse_descriptor(descriptor: &str) -> HttpResult<Self> {
         let parts: Vec<&str> = descriptor.split(':').collect();
-        ExtensionDescriptor {
+        if parts.len() != 3 || parts.iter().any(|it| it.is_empty()) {
+            return Err(HandlerError::new(
+                "Invalid extension descriptor".into(),
+                Some(format!("'{}' is not of the form <group>:<extension>:<version>.", descriptor)),
+                Status::BadRequest,
+            ));
+        }
+
+        check_coordinates(parts[0], parts[1], parts[2])?;
+
+        Ok(ExtensionDescriptor {
             group: parts[0].to_string(),
             extension: parts[1].to_string(),
             version: parts[2].to_string(),
+        })
+    }
+
+    pub fn as_key(&self) -> String {
+        format!("{}:{}:{}", self.group, self.extension, self.version)
+    }
+
+    pub fn identifier(&self) -> ExtensionIdentifier {
+        ExtensionIdentifier {
+            group: self.group.clone(),
+            name: self.extension.clone(),
         }
     }
 }
@@ -207,7 +357,129 @@ pub struct PartitionRuntimeModel {
     pub options: HashMap<String, String>,
 }
 
+// A published version that declares a parent on some version of another extension.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Dependent {
+    // `group:name:version` of the dependent version.
+    pub descriptor: String,
+    // The version of the parent it declares.
+    pub parent_version: String,
+}
+
+#[derive(Serialize)]
+pub struct DependentsResponse {
+    pub total: usize,
+    pub dependents: Vec<Dependent>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateCheckRequest {
+    // `release`, `alpha`, `beta`, `rc` or `snapshot`.
+    pub channel: String,
+    pub extensions: Vec<ExtensionDescriptor>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExtensionUpdate {
+    // The installed descriptor as it was sent.
+    pub installed: String,
+    // The newest version in the channel that is not yanked, if any.
+    pub latest: Option<String>,
+    pub update_available: bool,
+    pub yanked: bool,
+    // Registry paths of every published file of the latest version.
+    pub download_paths: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UpdateCheckResponse {
+    pub updates: Vec<ExtensionUpdate>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PublishResponse {
+    pub warnings: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateTokenRequest {
+    pub label: String,
+    // Who the token acts as, the label when not given.
+    #[serde(default)]
+    pub principal: Option<String>,
+    pub groups: Vec<String>,
+    pub scopes: Vec<Scope>,
+    // RFC 3339, tokens without an expiry are valid until they are revoked.
+    #[serde(default)]
+    pub expires: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TokenInfo {
+    pub id: String,
+    pub label: String,
+    pub principal: String,
+    pub groups: Vec<String>,
+    pub scopes: Vec<Scope>,
+    pub created: String,
+    pub expires: Option<String>,
+    pub last_used: Option<String>,
+}
+
+// The token itself is only ever part of this response.
+#[derive(Serialize, Deserialize)]
+pub struct CreatedToken {
+    pub token: String,
+    pub info: TokenInfo,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GroupOwners {
+    pub group: String,
+    pub owners: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct OwnerRequest {
+    pub principal: String,
+}
+
 #[derive(Serialize)]
 pub struct SearchResponse {
     pub result: Vec<ExtensionIdentifier>,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::{check_coordinates, VersionType};
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(VersionType::classify("1.2.0").unwrap(), VersionType::Release);
+        assert_eq!(VersionType::classify("1.2.0+build.4").unwrap(), VersionType::Release);
+        assert_eq!(VersionType::classify("1.2.0-alpha").unwrap(), VersionType::Alpha);
+        assert_eq!(VersionType::classify("1.2.0-beta3").unwrap(), VersionType::Beta);
+        assert_eq!(VersionType::classify("1.2.0-BETA").unwrap(), VersionType::Beta);
+        assert_eq!(VersionType::classify("1.2.0-rc.2").unwrap(), VersionType::ReleaseCandidate);
+        assert_eq!(VersionType::classify("1.2.0-SNAPSHOT").unwrap(), VersionType::Snapshot);
+
+        assert!(VersionType::classify("1.2.0-gamma").is_err());
+        assert!(VersionType::classify("1.2.0-rc.two").is_err());
+        assert!(VersionType::classify("1.2.0-").is_err());
+        assert!(VersionType::classify("1.2.0-SNAPSHOT.2").is_err());
+        assert!(VersionType::classify("1.2.0-snapshot3").is_err());
+    }
+
+    #[test]
+    fn test_check_coordinates() {
+        assert!(check_coordinates("com.example", "fish_monger-2", "1.2.0-rc.2").is_ok());
+
+        assert!(check_coordinates("com.example", "../../tmp/pwn", "1.0").is_err());
+        assert!(check_coordinates("com.example", "..", "1.0").is_err());
+        assert!(check_coordinates("com..example", "name", "1.0").is_err());
+        assert!(check_coordinates("com/example", "name", "1.0").is_err());
+        assert!(check_coordinates("com.example", "name\\x", "1.0").is_err());
+        assert!(check_coordinates("com.example", "name", "1.0+../../x").is_err());
+        assert!(check_coordinates("com.example", "name", "1.0+build.4").is_err());
+    }
+}
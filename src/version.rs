@@ -0,0 +1,312 @@
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+use rocket::http::Status;
+
+use crate::responses::{HandlerError, HttpResult};
+
+// A leniently parsed semantic version, `1.2`, `1.2.0` and `1.2.0-rc` are all valid.
+// Missing numeric components count as 0 and build metadata after a `+` is ignored.
+#[derive(Debug, Clone)]
+pub struct Version {
+    components: Vec<u64>,
+    pre_release: Vec<String>,
+}
+
+impl Version {
+    pub fn parse(version: &str) -> Option<Version> {
+        let version = version.split('+').next().unwrap();
+        let (numbers, pre_release) = match version.split_once('-') {
+            Some((numbers, pre_release)) => (numbers, Some(pre_release)),
+            None => (version, None),
+        };
+
+        let components = numbers.split('.')
+            .map(|it| it.parse::<u64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+
+        let pre_release = match pre_release {
+            Some("") => return None,
+            Some(pre_release) => pre_release.split(['.', '-'])
+                .flat_map(split_identifier)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Some(Version {
+            components,
+            pre_release,
+        })
+    }
+
+    pub fn parse_or_reject(version: &str) -> HttpResult<Version> {
+        Self::parse(version).ok_or_else(|| HandlerError::new(
+            "Invalid extension version".into(),
+            Some(format!("'{}' is not a valid version, versions are numbers separated by '.' with an optional '-' suffix.", version)),
+            Status::BadRequest,
+        ))
+    }
+
+    fn release(components: Vec<u64>) -> Version {
+        Version {
+            components,
+            pre_release: Vec::new(),
+        }
+    }
+
+    pub fn is_pre_release(&self) -> bool {
+        !self.pre_release.is_empty()
+    }
+
+    fn component(&self, index: usize) -> u64 {
+        self.components.get(index).copied().unwrap_or(0)
+    }
+}
+
+// Splits a trailing number off an identifier so `beta3` orders like `beta.3`.
+fn split_identifier(identifier: &str) -> Vec<String> {
+    let identifier = identifier.to_lowercase();
+    match identifier.find(|it: char| it.is_ascii_digit()) {
+        Some(index) if index > 0 && identifier[index..].chars().all(|it| it.is_ascii_digit()) => {
+            vec![identifier[..index].to_string(), identifier[index..].to_string()]
+        }
+        _ => vec![identifier],
+    }
+}
+
+// Numeric identifiers compare numerically and always sort before alphanumeric ones, as in semver.
+fn compare_identifiers(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let length = self.components.len().max(other.components.len());
+        let numbers = (0..length)
+            .map(|it| self.component(it).cmp(&other.component(it)))
+            .find(|it| it.is_ne())
+            .unwrap_or(Ordering::Equal);
+        if numbers.is_ne() {
+            return numbers;
+        }
+
+        // A pre-release always comes before the release it precedes.
+        match (self.is_pre_release(), other.is_pre_release()) {
+            (false, false) => return Ordering::Equal,
+            (false, true) => return Ordering::Greater,
+            (true, false) => return Ordering::Less,
+            (true, true) => {}
+        }
+
+        self.pre_release.iter()
+            .zip(other.pre_release.iter())
+            .map(|(a, b)| compare_identifiers(a, b))
+            .find(|it| it.is_ne())
+            .unwrap_or_else(|| self.pre_release.len().cmp(&other.pre_release.len()))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Version {}
+
+// A version a parent may declare, either an exact version or a range of them:
+// `[1.2,2.0)` maven style intervals where either bound may be left out, `^1.2` for every version up to the next
+// significant one, `1.2.+` for every version starting with `1.2.` and `+` for any version.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionRange {
+    Exact(Version),
+    Interval {
+        lower: Bound<Version>,
+        upper: Bound<Version>,
+    },
+}
+
+impl VersionRange {
+    pub fn parse(range: &str) -> Option<VersionRange> {
+        let range = range.trim();
+
+        if let Some(interval) = range.strip_prefix(['[', '(']) {
+            let lower_inclusive = range.starts_with('[');
+            let upper_inclusive = interval.ends_with(']');
+            let interval = interval.strip_suffix([']', ')'])?;
+
+            let Some((lower, upper)) = interval.split_once(',') else {
+                // `[1.0]` is the only way to write an exact version as an interval.
+                return (lower_inclusive && upper_inclusive)
+                    .then(|| Version::parse(interval.trim()))
+                    .flatten()
+                    .map(VersionRange::Exact);
+            };
+
+            let bound = |version: &str, inclusive: bool| -> Option<Bound<Version>> {
+                let version = version.trim();
+                if version.is_empty() {
+                    return Some(Bound::Unbounded);
+                }
+
+                let version = Version::parse(version)?;
+                Some(if inclusive { Bound::Included(version) } else { Bound::Excluded(version) })
+            };
+
+            return Some(VersionRange::Interval {
+                lower: bound(lower, lower_inclusive)?,
+                upper: bound(upper, upper_inclusive)?,
+            });
+        }
+
+        if let Some(version) = range.strip_prefix('^') {
+            let version = Version::parse(version)?;
+
+            // The first component that is not 0 is the one that may not change.
+            let significant = version.components.iter()
+                .position(|it| *it != 0)
+                .unwrap_or(version.components.len() - 1);
+            let mut components = version.components[..=significant].to_vec();
+            components[significant] += 1;
+
+            return Some(VersionRange::Interval {
+                lower: Bound::Included(version),
+                upper: Bound::Excluded(Version::release(components)),
+            });
+        }
+
+        if range == "+" {
+            return Some(VersionRange::Interval {
+                lower: Bound::Unbounded,
+                upper: Bound::Unbounded,
+            });
+        }
+
+        if let Some(prefix) = range.strip_suffix(".+") {
+            let components = prefix.split('.')
+                .map(|it| it.parse::<u64>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            let mut upper = components.clone();
+            *upper.last_mut().unwrap() += 1;
+
+            return Some(VersionRange::Interval {
+                lower: Bound::Included(Version::release(components)),
+                upper: Bound::Excluded(Version::release(upper)),
+            });
+        }
+
+        Version::parse(range).map(VersionRange::Exact)
+    }
+
+    pub fn is_exact(&self) -> bool {
+        matches!(self, VersionRange::Exact(_))
+    }
+
+    // Pre-releases only fall into a range that has a pre-release bound, `^1.2` never picks up `2.0-beta`.
+    pub fn matches(&self, version: &Version) -> bool {
+        let (lower, upper) = match self {
+            VersionRange::Exact(exact) => return exact == version,
+            VersionRange::Interval { lower, upper } => (lower, upper),
+        };
+
+        let bounds = [lower, upper].into_iter().filter_map(|it| match it {
+            Bound::Included(it) | Bound::Excluded(it) => Some(it),
+            Bound::Unbounded => None,
+        });
+        if version.is_pre_release() && !bounds.into_iter().any(|it| it.is_pre_release()) {
+            return false;
+        }
+
+        let above = match lower {
+            Bound::Included(lower) => version >= lower,
+            Bound::Excluded(lower) => version > lower,
+            Bound::Unbounded => true,
+        };
+        let below = match upper {
+            Bound::Included(upper) => version <= upper,
+            Bound::Excluded(upper) => version < upper,
+            Bound::Unbounded => true,
+        };
+
+        above && below
+    }
+}
+
+// Orders version strings, anything that does not parse sorts before every valid version.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (Version::parse(a), Version::parse(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::version::{compare_versions, Version, VersionRange};
+
+    #[test]
+    fn test_parse() {
+        assert!(Version::parse("1").is_some());
+        assert!(Version::parse("1.2.3-rc+build.5").is_some());
+        assert!(Version::parse("").is_none());
+        assert!(Version::parse("1.x").is_none());
+        assert!(Version::parse("1.0-").is_none());
+        assert!(Version::parse("v1.0").is_none());
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut versions = vec!["2.0.0", "1.0.1", "1.10", "1.2", "1.2-rc", "1.2-beta", "1.2.0-beta.2", "1.2.0-beta10", "1.2.0-alpha", "invalid"];
+        versions.sort_by(|a, b| compare_versions(a, b));
+
+        assert_eq!(versions, vec!["invalid", "1.0.1", "1.2.0-alpha", "1.2-beta", "1.2.0-beta.2", "1.2.0-beta10", "1.2-rc", "1.2", "1.10", "2.0.0"]);
+        assert_eq!(Version::parse("1.0"), Version::parse("1.0.0"));
+        assert_eq!(Version::parse("1.0-rc2"), Version::parse("1.0-RC.2"));
+    }
+
+    fn matches(range: &str, version: &str) -> bool {
+        VersionRange::parse(range).unwrap().matches(&Version::parse(version).unwrap())
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert!(matches("[1.2,2.0)", "1.2"));
+        assert!(matches("[1.2,2.0)", "1.9.9"));
+        assert!(!matches("[1.2,2.0)", "2.0"));
+        assert!(!matches("(1.2,2.0]", "1.2"));
+        assert!(matches("(1.2,2.0]", "2.0"));
+        assert!(matches("[1.2,)", "30.0"));
+        assert!(matches("[1.0]", "1.0.0"));
+
+        assert!(matches("^1.2", "1.9"));
+        assert!(!matches("^1.2", "2.0"));
+        assert!(!matches("^0.2.1", "0.3"));
+        assert!(!matches("^1.2", "1.5-beta"));
+        assert!(matches("[1.5-alpha,2.0)", "1.5-beta"));
+
+        assert!(matches("1.+", "1.0"));
+        assert!(matches("1.2.+", "1.2.7"));
+        assert!(!matches("1.2.+", "1.3"));
+        assert!(matches("+", "0.1"));
+
+        assert!(matches("1.0", "1.0"));
+        assert!(!matches("1.0", "1.1"));
+
+        for invalid in ["[1.0", "[1.0,2.0,3.0)", "(1.0)", "^", "x.+", "1.x", ""] {
+            assert!(VersionRange::parse(invalid).is_none(), "{}", invalid);
+        }
+    }
+}